@@ -0,0 +1,654 @@
+//! Conversion settings.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+use serde_json::Value;
+
+use number::{DefaultNumberParser, NumberParser, ParsedNumber};
+
+/// Key under which the text of an element with attributes is stored, unless overridden
+/// through `Node2ObjectConfig::text_key`.
+pub const DEFAULT_TEXT_KEY: &str = "text";
+
+/// Deepest level of nesting converted unless overridden through `Node2ObjectConfig::max_depth`;
+/// the same limit `serde_json` puts on the documents it parses.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Key holding the attribute list when `Node2ObjectConfig::attributes_as_list` is set.
+pub const ATTRIBUTE_LIST_KEY: &str = "@attributes";
+
+/// Key transformation of `KeyCase::Custom`.
+pub type KeyFn = Rc<dyn Fn(&str) -> String>;
+
+/// Strategy used to turn XML element and attribute names into JSON keys.
+#[derive(Clone)]
+pub enum KeyCase {
+    /// Pass names through `to_snake_case` (the default).
+    SnakeCase,
+    /// Keep names exactly as they appear in the document.
+    Verbatim,
+    /// Pass names through the given function.
+    Custom(KeyFn),
+}
+
+impl fmt::Debug for KeyCase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KeyCase::SnakeCase => f.write_str("SnakeCase"),
+            KeyCase::Verbatim => f.write_str("Verbatim"),
+            KeyCase::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// Representation of elements that carry no text, attributes or children.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmptyRepr {
+    /// `null` for the root element; empty children are left out of their parent.
+    Null,
+    /// An empty string, both for the root element and for children.
+    EmptyString,
+    /// An empty object, both for the root element and for children.
+    EmptyObject,
+    /// Left out, both the root element (giving an empty map) and children.
+    Omit,
+}
+
+/// Hook deciding the name of an element before conversion, see `pre_rename`.
+pub type RenameFn = Rc<dyn Fn(&str) -> Option<String>>;
+
+/// Decision on a number with a risk factor, see `on_ambiguous_number`.
+pub type AmbiguousNumberFn = Rc<dyn Fn(&str, ParsedNumber) -> Value>;
+
+/// Conversion of element text ahead of the built-in coercion, see `value_override`.
+pub type ValueOverrideFn = Rc<dyn Fn(&str, &str) -> Option<Value>>;
+
+/// Progress callback, see `on_progress`.
+pub type ProgressFn = Rc<dyn Fn(usize)>;
+
+/// What to do with strings longer than `max_string_len`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StringLimitPolicy {
+    /// Cut the string and append `truncation_marker`.
+    Truncate,
+    /// Fail with `ConvertError::StringTooLong` (through `try_node2object`; the infallible
+    /// functions truncate instead).
+    Error,
+}
+
+/// Target type of a value whose type is spelled out in the document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonType {
+    String,
+    Integer,
+    Float,
+    Bool,
+}
+
+/// Whether whitespace-only text counts as content when classifying elements.
+///
+/// treexml already drops whitespace-only text while parsing, so `<a/>`, `<a></a>` and
+/// `<a>  </a>` are all empty under either mode; the difference shows for trees built or
+/// modified in code, and for whitespace around child elements in mixed content.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmptyDetection {
+    /// Any text, even whitespace, is content (the default).
+    Strict,
+    /// Text and CDATA consisting only of whitespace are ignored.
+    IgnoreWhitespace,
+}
+
+/// Which repeated values `dedup_arrays` removes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DedupMode {
+    /// Values equal to the one right before them (the default).
+    Consecutive,
+    /// Values equal to any earlier one.
+    All,
+}
+
+/// What to do with an element listed in `value_attribute` that also has text or children.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueAttributeConflict {
+    /// Convert the element as usual, with the attribute among its other content (the default).
+    Convert,
+    /// Fail with `ConvertError::ValueAttributeConflict` (through `try_node2object`; the
+    /// infallible functions convert the element as usual instead).
+    Error,
+}
+
+/// How the namespace prefix of an element (`atom` in `atom:entry`) or attribute (`xml` in
+/// `xml:lang`) shows in its key. `xmlns` declarations never reach the converter, since the
+/// parser does not report them as attributes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NamespaceMode {
+    /// Drop the prefix and key elements by their local name (the default). Attribute names
+    /// are converted whole, prefix included (`xml:lang` gives `xml_lang`), unless the prefix
+    /// is listed in `strip_prefixes`.
+    Strip,
+    /// Keep the prefix verbatim in front of the converted local name: `atom:entry`, `xml:lang`.
+    Keep,
+    /// Group the children with a prefix under an object keyed by the prefix:
+    /// `"atom": {"entry": [...]}`. A root element and attributes keep their prefix as with
+    /// `Keep`.
+    Expand,
+}
+
+/// What to do when several `keyed_value` children of an element share a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyedDuplicatePolicy {
+    /// Collect their values into a list, in document order.
+    Array,
+    /// Keep the first value.
+    FirstWins,
+    /// Keep the last value (the default).
+    LastWins,
+    /// Fail with `ConvertError::DuplicateKey` (through `try_node2object`; the infallible
+    /// functions keep the last value instead).
+    Error,
+}
+
+/// Single representation for every number of the output, see `numeric_output`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumericOutput {
+    /// Floats, so `42` becomes `42.0`.
+    AllFloat,
+    /// Integers; fractional numbers are handled by `fractional_int_policy`.
+    AllInt,
+    /// Strings holding the number as it would be written in JSON.
+    AllString,
+}
+
+/// What `NumericOutput::AllInt` does with numbers that have a fractional part.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FractionalIntPolicy {
+    /// Round to the nearest integer, halves away from zero (the default).
+    Round,
+    /// Fail with `ConvertError::FractionalNumber` (through `try_node2object`; the
+    /// infallible functions round instead).
+    Error,
+}
+
+/// What `document2object` returns for a document without a root element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmptyDocument {
+    /// An empty object (the default).
+    EmptyMap,
+    /// `null`.
+    Null,
+    /// Fail with `ConvertError::EmptyDocument`.
+    Error,
+}
+
+/// Whitespace handling of element text, see `text_whitespace`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextWhitespace {
+    /// Keep text as it is (the default).
+    Keep,
+    /// Remove leading and trailing whitespace.
+    Trim,
+    /// Trim, and replace every inner run of whitespace with a single space.
+    Collapse,
+}
+
+/// What `coerce_numbers` does with text such as `NaN`, `inf` or `-infinity`, which parses
+/// as a number that JSON cannot represent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonFiniteNumbers {
+    /// Keep the text as a string (the default).
+    String,
+    /// Convert it to `null`.
+    Null,
+}
+
+/// What to do when an attribute and a child element of the same element produce the same
+/// key, as in `<item id="1"><id>2</id></item>`. An `attribute_prefix` avoids such clashes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeChildCollision {
+    /// Put the attribute value in front of the children's values: `"id": ["1", "2"]` (the
+    /// default).
+    Merge,
+    /// Fail with `ConvertError::AttributeCollision` (through `try_node2object`; the
+    /// infallible functions merge instead).
+    Error,
+}
+
+/// Settings controlling how `node2object_with` converts a tree.
+#[derive(Clone)]
+pub struct Node2ObjectConfig {
+    /// How element and attribute names are turned into keys.
+    pub key_case: KeyCase,
+    /// How empty elements are represented.
+    pub empty_element: EmptyRepr,
+    /// Parse numeric-looking text into JSON numbers. Off by default because `node2object`
+    /// never coerced, so text such as `007` keeps coming through as the string it was.
+    pub coerce_numbers: bool,
+    /// Apply `coerce_numbers` to attribute values as well as to text.
+    pub coerce_attributes: bool,
+    /// Treat attributes with an empty value, or a value equal to the attribute name
+    /// (`disabled=""`, `checked="checked"`), as `true`.
+    pub presence_attributes: bool,
+    /// Attributes that every element with the given tag name must carry. Checked by
+    /// `try_node2object`, which reports `ConvertError::MissingAttribute` otherwise.
+    pub required_attributes: HashMap<String, Vec<String>>,
+    /// Values of attributes that elements with the given tag name get when they lack them,
+    /// like XSD default attribute values. Defaults are used as they are, without coercion, and
+    /// make an element without attributes convert to an object.
+    pub attribute_defaults: HashMap<String, HashMap<String, Value>>,
+    /// Output keys to use for specific tag names instead of the `key_case` transformation.
+    /// `try_node2object` reports `ConvertError::KeyCollision` if an alias clashes with a
+    /// key produced by another attribute or tag of the same element.
+    pub key_aliases: HashMap<String, String>,
+    /// Replacements for element keys after `key_case`, for keys that clash with reserved words
+    /// downstream. Maps `option` to `option_tag` by default; clear it to keep every key as
+    /// `key_case` makes it. Only used with `KeyCase::SnakeCase`: `Verbatim` keeps tag names as
+    /// they are and a `Custom` function can rename keys itself. Keys from `key_aliases` are
+    /// used as they are.
+    pub key_remap: HashMap<String, String>,
+    /// Key holding the text of elements that also carry attributes. `object2node_with`
+    /// reads the text back from the same key.
+    pub text_key: String,
+    /// When set, `coerce_numbers` only applies to elements (and their attributes) with
+    /// one of these tag names.
+    pub coerce_only: Option<HashSet<String>>,
+    /// Character separating the integer and fractional parts of numbers.
+    pub decimal_separator: char,
+    /// Digit-grouping character stripped before parsing numbers, as in `1,234.56`. Only
+    /// honored for tags listed in `coerce_only`, and only if every group after the first has
+    /// exactly three digits, since a stray comma would otherwise silently change the value.
+    pub thousands_separator: Option<char>,
+    /// Maximum length in bytes of element text and attribute values.
+    pub max_string_len: Option<usize>,
+    /// What to do with strings longer than `max_string_len`.
+    pub max_string_policy: StringLimitPolicy,
+    /// Appended to strings cut by `StringLimitPolicy::Truncate`.
+    pub truncation_marker: String,
+    /// Emit every coerced number as `{"$num": 1.5, "$raw": "1.50"}`, keeping the source text
+    /// next to the parsed value. This makes the output considerably more verbose.
+    pub dual_number: bool,
+    /// Keep coerced numbers whose text would not come back the same (`007`, `+5`, `1.50`,
+    /// `1e5`) as `{"$raw": "007"}`, so `object2node_with`
+    /// restores the exact text. Numbers that survive as they are stay plain numbers.
+    pub preserve_number_format: bool,
+    /// Namespace prefixes removed from attribute names, so `soap:encodingStyle` becomes
+    /// `encodingStyle`. Element names never include their prefix.
+    pub strip_prefixes: HashSet<String>,
+    /// Slash-separated path of output keys, starting with the root (like `extract_records`).
+    /// When it resolves, the first element found there is converted instead of the root and
+    /// its object is returned as is. Documents where it does not resolve convert normally.
+    pub unwrap_path: Option<String>,
+    /// Renames elements before anything else looks at them, so the returned name is what
+    /// tag-based settings (`coerce_only`, `key_aliases`, ...) and the output key are derived
+    /// from. Returning `None` leaves the element and its subtree out; the root element
+    /// cannot be left out and keeps its own name in that case.
+    pub pre_rename: Option<RenameFn>,
+    /// Tag name suffixes that spell out the type of the element text, as in `<count_i>` or
+    /// `<code_s>`. The text of a matching element is converted to that type regardless of
+    /// `coerce_numbers`, and stays a string if it does not parse.
+    pub type_suffix_map: HashMap<String, JsonType>,
+    /// Drop the matched `type_suffix_map` suffix from output keys, so `<count_i>` yields `count`.
+    pub strip_type_suffix: bool,
+    /// Inserted between the text and the CDATA of an element that has both. By default
+    /// they are joined directly.
+    pub text_cdata_separator: Option<String>,
+    /// Keep values shaped like ISO 8601 / XSD dates and times as strings even when
+    /// `coerce_numbers` is set. This covers `gYear` values, so any plain four-digit integer is
+    /// treated as a year.
+    pub preserve_date_like: bool,
+    /// Repeated tags holding time-series samples, with the fields to keep. Instead of an
+    /// array of sample objects, such a tag yields one array per field, e.g.
+    /// `{"t": [1, 2], "v": [10, 20]}`. Samples lacking a field make `try_node2object` fail
+    /// with `ConvertError::MissingField`; the infallible functions fill in `null`.
+    pub timeseries: HashMap<String, Vec<String>>,
+    /// Text and attribute values that mean `null`, such as `NULL` or `nil`. Empty by default.
+    pub null_tokens: HashSet<String>,
+    /// Match `null_tokens` ignoring ASCII case.
+    pub null_tokens_ignore_case: bool,
+    /// Whether whitespace-only text makes an element non-empty.
+    pub empty_detection: EmptyDetection,
+    /// Replacements for whole values that are still strings after coercion, e.g. `"N/A"` to
+    /// `null`. Applied to element text and coerced attribute values.
+    pub value_replacements: HashMap<String, Value>,
+    /// Called with the number of elements visited so far, every `progress_interval` elements.
+    pub on_progress: Option<ProgressFn>,
+    /// Number of elements between two `on_progress` calls.
+    pub progress_interval: usize,
+    /// Emit attributes as a list of `{"name": ..., "value": ...}` objects under
+    /// `ATTRIBUTE_LIST_KEY`, so names that become equal keys (e.g. through `strip_prefixes`)
+    /// all survive. treexml keeps attributes in a `HashMap`, so source order and literal
+    /// duplicates are already gone after parsing; entries are sorted by name instead.
+    pub attributes_as_list: bool,
+    /// `(type tag, value tag)` for documents that spell out types in a sibling element, as in
+    /// `<field><type>int</type><value>42</value></field>`. An element with both children
+    /// converts to the value alone, typed as named (`int`, `float`, `bool`, anything else
+    /// meaning string); its other children and attributes are dropped.
+    pub type_from_sibling: Option<(String, String)>,
+    /// When coercing a value involves a judgment call (a number that looks like a date and
+    /// is kept as a string, or a number whose text would not survive, like `007`), emit
+    /// `{"value": <chosen>, "#raw": <original text>}` instead of the bare value.
+    pub raw_fallback: bool,
+    /// Treat `id` and `idref` attributes as links: an element with an `idref` gets the
+    /// converted element carrying that `id` under `#ref`, or `null` if there is none. Each
+    /// element is inlined at its first reference only, so shared references cannot blow up
+    /// the output; later references, and references back to an element being inlined, yield
+    /// the bare id instead. Inlined strings count towards `max_total_string_bytes`.
+    pub resolve_idrefs: bool,
+    /// When set, only elements with one of these namespace prefixes (`dc` for `dc:title`) are
+    /// converted; other elements and their subtrees are left out, except for the root.
+    /// treexml does not keep namespace URIs, so elements are matched by prefix, and documents
+    /// binding a different prefix to the same namespace need it listed as well.
+    pub namespace_filter: Option<HashSet<String>>,
+    /// Top-level key of the output, instead of the one derived from the root tag name. Paths
+    /// such as `unwrap_path` and `extract_records` item paths start with this key too.
+    pub root_key: Option<String>,
+    /// Tags of repeated attribute-pair elements, with their `(key attribute, value attribute)`.
+    /// Such a child adds one entry to its parent, keyed by the verbatim key attribute and
+    /// holding the value attribute (coerced like any attribute), so `<metric name="cpu"
+    /// value="0.8"/>` yields `"cpu": 0.8`. Children lacking the key attribute convert normally.
+    /// An entry whose key is also that of child elements is handled by
+    /// `attribute_child_collision`, like an attribute.
+    pub keyed_value: HashMap<String, (String, String)>,
+    /// What to do when several `keyed_value` children of an element share a key.
+    pub keyed_duplicate_policy: KeyedDuplicatePolicy,
+    /// Check names more strictly than the parser: `try_node2object` rejects invalid or empty
+    /// element and attribute names (possible in trees built in code) with
+    /// `ConvertError::InvalidName`, and attributes whose names produce the same key (such as
+    /// `userId` and `user_id`) with `ConvertError::DuplicateAttribute`. Literally repeated
+    /// attribute names are already rejected while parsing.
+    pub strict_wellformed: bool,
+    /// Limit on the total bytes of keys, text and attribute values taken from the document,
+    /// across the whole conversion. `try_node2object` fails with
+    /// `ConvertError::StringBudgetExceeded` once it is passed; the other functions ignore it.
+    /// Sizes are counted after entity expansion. The parser rejects documents declaring
+    /// entities in a DTD, so only predefined entities and character references expand, and
+    /// "billion laughs" style blowups cannot get as far as conversion.
+    pub max_total_string_bytes: Option<usize>,
+    /// Tags whose lists drop repeated scalar values, as chosen by `dedup_mode`. The first of
+    /// each run is kept, so the remaining values stay in document order; objects and arrays
+    /// are never removed.
+    pub dedup_arrays: HashSet<String>,
+    /// Which repeated values `dedup_arrays` removes.
+    pub dedup_mode: DedupMode,
+    /// Attribute holding the whole value of elements with the given tag, for pointer-style
+    /// elements like `<ref id="42"/>`: such an element converts to the attribute's coerced
+    /// value alone, and its other attributes are dropped.
+    pub value_attribute: HashMap<String, String>,
+    /// What to do with a `value_attribute` element that also has text or children.
+    pub value_attribute_conflict: ValueAttributeConflict,
+    /// How numeric text is turned into values once `coerce_numbers` decides to coerce it.
+    /// `DefaultNumberParser` keeps integers as integers and parses the rest as `f64`.
+    pub number_parser: Rc<dyn NumberParser>,
+    /// How element prefixes show in keys. treexml keeps the prefix apart from the local name
+    /// and drops the namespace URI, so only the prefix itself can be kept. Attribute names
+    /// are unaffected and keep their prefix, see `strip_prefixes`.
+    pub namespace_mode: NamespaceMode,
+    /// Text and attribute values that mean `true` or `false`, matched ignoring case.
+    pub bool_tokens: HashMap<String, bool>,
+    /// Language code (`fr`, or `fr-CA` with a region) whose yes/no words count as booleans on
+    /// top of `bool_tokens`: `fr` (oui/non), `de` (ja/nein), `ru` (да/нет) and `es` (sí/no,
+    /// also `si` without the accent). Other codes add nothing.
+    pub bool_locale: Option<String>,
+    /// Next to every list of repeated children, add a key holding its length, named after the
+    /// list's key with `#count` appended (`"entry#count": 2`). Meant for checking
+    /// cardinalities while debugging; the extra keys are not understood by `object2node_with`.
+    pub annotate_counts: bool,
+    /// Types to try in order for every text and coerced attribute value, replacing the
+    /// default of numbers (with `coerce_numbers`) and `bool_tokens`. The first type the value
+    /// converts to wins, `JsonType::String` ending the chain; `Bool` also accepts `true`,
+    /// `false`, `1` and `0`. `coerce_only` and `preserve_date_like` still apply, while
+    /// `dual_number`, `raw_fallback` and `number_parser` do not.
+    pub coercion_chain: Option<Vec<JsonType>>,
+    /// Rewrite every number of the result into one representation, after everything else.
+    /// Applies to `node2object_with` and `try_node2object`, including numbers inside objects
+    /// such as those of `dual_number`.
+    pub numeric_output: Option<NumericOutput>,
+    /// What `NumericOutput::AllInt` does with fractional numbers.
+    pub fractional_int_policy: FractionalIntPolicy,
+    /// Prepended to the keys of attributes, e.g. `@` or `_attr_`, to tell them apart from
+    /// children. Empty by default, giving bare keys; `attributes_as_list` names are never
+    /// prefixed. `object2node_with` strips it again.
+    pub attribute_prefix: String,
+    /// What `document2object` returns for a document without a root element.
+    pub empty_document: EmptyDocument,
+    /// Whitespace handling of element text (including CDATA) before coercion. Attribute
+    /// values are left alone.
+    pub text_whitespace: TextWhitespace,
+    /// Tag names whose text is kept verbatim whatever `text_whitespace` says, e.g. `pre`.
+    pub preserve_whitespace_tags: HashSet<String>,
+    /// Convert an element whose only contents are children with one tag name, all of them
+    /// scalars, to the bare list of their values: `<colors><c>red</c><c>green</c></colors>`
+    /// becomes `["red", "green"]` instead of `{"c": ["red", "green"]}`. Lists of objects keep
+    /// their structure.
+    pub auto_unwrap_scalar_lists: bool,
+    /// Called with the text of values that convert to a number but might have been meant as
+    /// strings (see `NumberRisk`); the returned value is used as is. Date-like text only gets
+    /// here without `preserve_date_like`, which always keeps it a string.
+    pub on_ambiguous_number: Option<AmbiguousNumberFn>,
+    /// Only coerce numbers that are written back exactly as they appear, keeping text such as
+    /// zip codes (`01234`), `000`, `+5` or `1.50` a string. The comparison uses the text after
+    /// the separator settings are applied.
+    pub canonical_numbers_only: bool,
+    /// What `coerce_numbers` does with `NaN` and infinities. They never reach `number_parser`.
+    pub non_finite_numbers: NonFiniteNumbers,
+    /// Deepest level of nesting converted, counting the root as 1; `DEFAULT_MAX_DEPTH` by
+    /// default, where it used to be unlimited. Conversion recurses once per level, so only
+    /// lift the limit with `None` for trusted documents, or a deep enough one overflows the
+    /// stack. `try_node2object` reports deeper elements as `ConvertError::TooDeep`; the
+    /// infallible functions put `{"#truncated": true}` in their place.
+    pub max_depth: Option<usize>,
+    /// Store children whose tag occurs once in their parent as a bare value rather than a
+    /// one-item list: `{"b": 1}` instead of `{"b": [1]}`. Keys in `force_list` stay lists.
+    pub collapse_single_element_arrays: bool,
+    /// Keys (as emitted, so after `key_case`) of children that always convert to a list, even
+    /// when their tag occurs once. Children are lists by default anyway; this wins over
+    /// `collapse_single_element_arrays`, which would otherwise unwrap a single child.
+    pub force_list: HashSet<String>,
+    /// Keep the CDATA of elements apart from their text: such elements convert to an object
+    /// with the text (if any) under `text_key` and the CDATA, verbatim, under `#cdata`.
+    /// `text_cdata_separator` no longer applies. `object2node_with` restores the CDATA.
+    pub preserve_cdata: bool,
+    /// Called with the slash-separated path of the element (as in `population/entry/name`)
+    /// and its text before any coercion of element text; a returned value is used as is,
+    /// while `None` leaves the text to the other settings.
+    pub value_override: Option<ValueOverrideFn>,
+    /// What to do when an attribute (or a `keyed_value` entry) and a child element produce the
+    /// same key.
+    pub attribute_child_collision: AttributeChildCollision,
+}
+
+/// Stands in for a hook in the `Debug` output of `Node2ObjectConfig`.
+struct Hook;
+
+impl fmt::Debug for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("..")
+    }
+}
+
+impl fmt::Debug for Node2ObjectConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node2ObjectConfig")
+            .field("key_case", &self.key_case)
+            .field("empty_element", &self.empty_element)
+            .field("coerce_numbers", &self.coerce_numbers)
+            .field("coerce_attributes", &self.coerce_attributes)
+            .field("presence_attributes", &self.presence_attributes)
+            .field("required_attributes", &self.required_attributes)
+            .field("attribute_defaults", &self.attribute_defaults)
+            .field("key_aliases", &self.key_aliases)
+            .field("key_remap", &self.key_remap)
+            .field("text_key", &self.text_key)
+            .field("coerce_only", &self.coerce_only)
+            .field("decimal_separator", &self.decimal_separator)
+            .field("thousands_separator", &self.thousands_separator)
+            .field("max_string_len", &self.max_string_len)
+            .field("max_string_policy", &self.max_string_policy)
+            .field("truncation_marker", &self.truncation_marker)
+            .field("dual_number", &self.dual_number)
+            .field("preserve_number_format", &self.preserve_number_format)
+            .field("strip_prefixes", &self.strip_prefixes)
+            .field("unwrap_path", &self.unwrap_path)
+            .field("pre_rename", &self.pre_rename.as_ref().map(|_| Hook))
+            .field("type_suffix_map", &self.type_suffix_map)
+            .field("strip_type_suffix", &self.strip_type_suffix)
+            .field("text_cdata_separator", &self.text_cdata_separator)
+            .field("preserve_date_like", &self.preserve_date_like)
+            .field("timeseries", &self.timeseries)
+            .field("null_tokens", &self.null_tokens)
+            .field("null_tokens_ignore_case", &self.null_tokens_ignore_case)
+            .field("empty_detection", &self.empty_detection)
+            .field("value_replacements", &self.value_replacements)
+            .field("on_progress", &self.on_progress.as_ref().map(|_| Hook))
+            .field("progress_interval", &self.progress_interval)
+            .field("attributes_as_list", &self.attributes_as_list)
+            .field("type_from_sibling", &self.type_from_sibling)
+            .field("raw_fallback", &self.raw_fallback)
+            .field("resolve_idrefs", &self.resolve_idrefs)
+            .field("namespace_filter", &self.namespace_filter)
+            .field("root_key", &self.root_key)
+            .field("keyed_value", &self.keyed_value)
+            .field("keyed_duplicate_policy", &self.keyed_duplicate_policy)
+            .field("strict_wellformed", &self.strict_wellformed)
+            .field("max_total_string_bytes", &self.max_total_string_bytes)
+            .field("dedup_arrays", &self.dedup_arrays)
+            .field("dedup_mode", &self.dedup_mode)
+            .field("value_attribute", &self.value_attribute)
+            .field("value_attribute_conflict", &self.value_attribute_conflict)
+            .field("number_parser", &Hook)
+            .field("namespace_mode", &self.namespace_mode)
+            .field("bool_tokens", &self.bool_tokens)
+            .field("bool_locale", &self.bool_locale)
+            .field("annotate_counts", &self.annotate_counts)
+            .field("coercion_chain", &self.coercion_chain)
+            .field("numeric_output", &self.numeric_output)
+            .field("fractional_int_policy", &self.fractional_int_policy)
+            .field("attribute_prefix", &self.attribute_prefix)
+            .field("empty_document", &self.empty_document)
+            .field("text_whitespace", &self.text_whitespace)
+            .field("preserve_whitespace_tags", &self.preserve_whitespace_tags)
+            .field("auto_unwrap_scalar_lists", &self.auto_unwrap_scalar_lists)
+            .field("on_ambiguous_number", &self.on_ambiguous_number.as_ref().map(|_| Hook))
+            .field("canonical_numbers_only", &self.canonical_numbers_only)
+            .field("non_finite_numbers", &self.non_finite_numbers)
+            .field("max_depth", &self.max_depth)
+            .field("collapse_single_element_arrays", &self.collapse_single_element_arrays)
+            .field("force_list", &self.force_list)
+            .field("preserve_cdata", &self.preserve_cdata)
+            .field("value_override", &self.value_override.as_ref().map(|_| Hook))
+            .field("attribute_child_collision", &self.attribute_child_collision)
+            .finish()
+    }
+}
+
+impl Default for Node2ObjectConfig {
+    fn default() -> Self {
+        Node2ObjectConfig {
+            key_case: KeyCase::SnakeCase,
+            empty_element: EmptyRepr::Null,
+            coerce_numbers: false,
+            coerce_attributes: true,
+            presence_attributes: false,
+            required_attributes: HashMap::new(),
+            attribute_defaults: HashMap::new(),
+            key_aliases: HashMap::new(),
+            key_remap: [("option".to_string(), "option_tag".to_string())].iter().cloned().collect(),
+            text_key: DEFAULT_TEXT_KEY.to_string(),
+            coerce_only: None,
+            decimal_separator: '.',
+            thousands_separator: None,
+            max_string_len: None,
+            max_string_policy: StringLimitPolicy::Truncate,
+            truncation_marker: "…".to_string(),
+            dual_number: false,
+            preserve_number_format: false,
+            strip_prefixes: HashSet::new(),
+            unwrap_path: None,
+            pre_rename: None,
+            type_suffix_map: HashMap::new(),
+            strip_type_suffix: false,
+            text_cdata_separator: None,
+            preserve_date_like: false,
+            timeseries: HashMap::new(),
+            null_tokens: HashSet::new(),
+            null_tokens_ignore_case: false,
+            empty_detection: EmptyDetection::Strict,
+            value_replacements: HashMap::new(),
+            on_progress: None,
+            progress_interval: 1000,
+            attributes_as_list: false,
+            type_from_sibling: None,
+            raw_fallback: false,
+            resolve_idrefs: false,
+            namespace_filter: None,
+            root_key: None,
+            keyed_value: HashMap::new(),
+            keyed_duplicate_policy: KeyedDuplicatePolicy::LastWins,
+            strict_wellformed: false,
+            max_total_string_bytes: None,
+            dedup_arrays: HashSet::new(),
+            dedup_mode: DedupMode::Consecutive,
+            value_attribute: HashMap::new(),
+            value_attribute_conflict: ValueAttributeConflict::Convert,
+            number_parser: Rc::new(DefaultNumberParser),
+            namespace_mode: NamespaceMode::Strip,
+            bool_tokens: HashMap::new(),
+            bool_locale: None,
+            annotate_counts: false,
+            coercion_chain: None,
+            numeric_output: None,
+            fractional_int_policy: FractionalIntPolicy::Round,
+            attribute_prefix: String::new(),
+            empty_document: EmptyDocument::EmptyMap,
+            text_whitespace: TextWhitespace::Keep,
+            preserve_whitespace_tags: HashSet::new(),
+            auto_unwrap_scalar_lists: false,
+            on_ambiguous_number: None,
+            canonical_numbers_only: false,
+            non_finite_numbers: NonFiniteNumbers::String,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            collapse_single_element_arrays: false,
+            force_list: HashSet::new(),
+            preserve_cdata: false,
+            value_override: None,
+            attribute_child_collision: AttributeChildCollision::Merge,
+        }
+    }
+}
+
+impl Node2ObjectConfig {
+    /// Settings for scraping XHTML. Compared to the defaults, this:
+    ///
+    /// * sets `presence_attributes`, so `<input disabled="disabled"/>` yields `"disabled": true`;
+    /// * uses `EmptyRepr::EmptyString`, so `<td></td>` yields `""` instead of being dropped;
+    /// * uses `KeyCase::Verbatim`, so tag and attribute names keep their case;
+    /// * clears `coerce_attributes`, so attribute values stay strings even if
+    ///   `coerce_numbers` is turned on later.
+    pub fn html_compat() -> Self {
+        Node2ObjectConfig {
+            key_case: KeyCase::Verbatim,
+            empty_element: EmptyRepr::EmptyString,
+            coerce_attributes: false,
+            presence_attributes: true,
+            ..Node2ObjectConfig::default()
+        }
+    }
+
+    /// Settings for SOAP messages. Compared to the defaults, this:
+    ///
+    /// * strips the `soap:`, `soapenv:` and `SOAP-ENV:` prefixes from attribute names;
+    /// * sets `unwrap_path` to `Envelope/Body`, so the output holds the body contents only;
+    ///   set it back to `None` to keep the envelope and header;
+    /// * uses `KeyCase::Verbatim`, so element names keep their case.
+    ///
+    /// Numbers are not coerced, so identifiers such as `00042` stay strings.
+    pub fn soap() -> Self {
+        Node2ObjectConfig {
+            key_case: KeyCase::Verbatim,
+            strip_prefixes: ["soap", "soapenv", "SOAP-ENV"].iter().map(|p| p.to_string()).collect(),
+            unwrap_path: Some("Envelope/Body".to_string()),
+            ..Node2ObjectConfig::default()
+        }
+    }
+}
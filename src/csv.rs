@@ -0,0 +1,74 @@
+//! CSV output for record lists.
+
+use serde_json::Value;
+use treexml;
+
+use {extract_records, Node2ObjectConfig};
+
+/// Text of a leaf scalar. Single-element arrays, as produced for child elements, are
+/// looked through; anything else that is not a scalar yields an empty cell.
+fn cell(v: Option<&Value>) -> String {
+    match v {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => n.to_string(),
+        Some(Value::Bool(b)) => b.to_string(),
+        Some(Value::Array(a)) if a.len() == 1 => cell(a.first()),
+        _ => String::new(),
+    }
+}
+
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn push_row<I: Iterator<Item = String>>(out: &mut String, fields: I) {
+    let row: Vec<String> = fields.map(|f| escape(&f)).collect();
+    out.push_str(&row.join(","));
+    out.push('\n');
+}
+
+/// Converts the records at `item_path` (see `extract_records`) into CSV with a header row
+/// followed by one row per record. Each column is looked up as a key of the record;
+/// missing columns and non-scalar values produce empty cells.
+pub fn to_csv(e: &treexml::Element, item_path: &str, columns: &[&str]) -> String {
+    let mut out = String::new();
+    push_row(&mut out, columns.iter().map(|c| c.to_string()));
+    for record in extract_records(e, item_path, &Node2ObjectConfig::default()) {
+        push_row(&mut out, columns.iter().map(|c| cell(record.get(*c))));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn population_to_csv() {
+        let dom_root = treexml::Document::parse("
+            <population>
+              <entry>
+                <name>Alex</name>
+                <height>173.5</height>
+              </entry>
+              <entry>
+                <name>Mel</name>
+                <height>180.4</height>
+              </entry>
+            </population>
+        ".as_bytes()).unwrap().root.unwrap();
+
+        assert_eq!(
+            to_csv(&dom_root, "population/entry", &["name", "height"]),
+            "name,height\nAlex,173.5\nMel,180.4\n"
+        );
+        assert_eq!(
+            to_csv(&dom_root, "population/entry", &["name", "age"]),
+            "name,age\nAlex,\nMel,\n"
+        );
+    }
+}
@@ -0,0 +1,390 @@
+//! Deserialization straight from treexml elements into Rust types, see `from_element`.
+
+use std::collections::{btree_map, BTreeMap};
+use std::vec;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::Value;
+use treexml;
+
+use {
+    attribute_key, element_key, element_text, parse_attribute, parse_text, root_key, scan_xml_node, tag_name,
+    try_node2object, ConvertError, Node2ObjectConfig, XMLNodeType,
+};
+
+/// Adds the element `name` in front of the path of an error raised inside it.
+fn within(err: ConvertError, name: &str) -> ConvertError {
+    let prefix = |path: String| {
+        if path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", name, path)
+        }
+    };
+    match err {
+        ConvertError::Deserialize { path, message } => ConvertError::Deserialize { path: prefix(path), message },
+        ConvertError::TooDeep { path, max } => ConvertError::TooDeep { path: prefix(path), max },
+        err => err,
+    }
+}
+
+fn json_error(err: serde_json::Error) -> ConvertError {
+    de::Error::custom(err)
+}
+
+/// Value of one key of a converted element.
+enum Entry<'e> {
+    /// An attribute or the text, converted as `node2object` converts it.
+    Scalar(Value),
+    /// The children with one key, after the value of an attribute with the same key if any.
+    List(Option<Value>, Vec<&'e treexml::Element>),
+    /// A top-level element.
+    Element(&'e treexml::Element),
+}
+
+/// The keys of `e` as `node2object` produces them, in the order of the output map.
+fn entries<'e>(e: &'e treexml::Element, cfg: &Node2ObjectConfig) -> BTreeMap<String, Entry<'e>> {
+    let node_type = scan_xml_node(e, cfg.empty_detection);
+    let mut entries = BTreeMap::new();
+    for (k, v) in &e.attributes {
+        entries.insert(attribute_key(k, cfg), Entry::Scalar(parse_attribute(&e.name, k, v, cfg)));
+    }
+    if let XMLNodeType::Parent | XMLNodeType::SemiStructured = node_type {
+        for c in &e.children {
+            let name = match tag_name(c, cfg) {
+                Some(name) => name,
+                None => continue,
+            };
+            if scan_xml_node(c, cfg.empty_detection) == XMLNodeType::Empty {
+                continue;
+            }
+            match entries.entry(element_key(c, &name, cfg)) {
+                btree_map::Entry::Vacant(slot) => {
+                    slot.insert(Entry::List(None, vec![c]));
+                }
+                btree_map::Entry::Occupied(mut slot) => match *slot.get_mut() {
+                    Entry::List(_, ref mut items) => items.push(c),
+                    ref mut entry => {
+                        let attribute = match *entry {
+                            Entry::Scalar(ref mut v) => Some(v.take()),
+                            _ => None,
+                        };
+                        *entry = Entry::List(attribute, vec![c]);
+                    }
+                },
+            }
+        }
+    }
+    if let XMLNodeType::TextAndAttributes | XMLNodeType::SemiStructured = node_type {
+        let text = parse_text(&element_text(e, ""), &e.name, cfg);
+        entries.insert(cfg.text_key.clone(), Entry::Scalar(text));
+    }
+    entries
+}
+
+/// The converted value of an element.
+struct ElementDeserializer<'e, 'c> {
+    e: &'e treexml::Element,
+    /// Level of `e`, counting the root as 1 like `max_depth`.
+    depth: usize,
+    cfg: &'c Node2ObjectConfig,
+}
+
+impl<'e, 'c> ElementDeserializer<'e, 'c> {
+    fn node_type(&self) -> XMLNodeType {
+        scan_xml_node(self.e, self.cfg.empty_detection)
+    }
+
+    fn text(&self) -> Value {
+        parse_text(&element_text(self.e, ""), &self.e.name, self.cfg)
+    }
+}
+
+impl<'de, 'e, 'c> de::Deserializer<'de> for ElementDeserializer<'e, 'c> {
+    type Error = ConvertError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+        let result = match (self.cfg.max_depth, self.node_type()) {
+            (Some(max), _) if self.depth > max => Err(ConvertError::TooDeep { path: String::new(), max }),
+            (_, XMLNodeType::Empty) => visitor.visit_unit(),
+            (_, XMLNodeType::Text) => de::Deserializer::deserialize_any(self.text(), visitor).map_err(json_error),
+            _ => visitor.visit_map(EntriesAccess {
+                entries: entries(self.e, self.cfg).into_iter(),
+                pending: None,
+                depth: self.depth,
+                cfg: self.cfg,
+            }),
+        };
+        result.map_err(|err| within(err, &self.e.name))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+        match self.node_type() {
+            XMLNodeType::Empty => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ConvertError> {
+        match self.node_type() {
+            XMLNodeType::Text => de::Deserializer::deserialize_enum(self.text(), name, variants, visitor)
+                .map_err(|err| within(json_error(err), &self.e.name)),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V) -> Result<V::Value, ConvertError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// The keys of a converted element.
+struct EntriesAccess<'e, 'c> {
+    entries: btree_map::IntoIter<String, Entry<'e>>,
+    /// Value of the key handed out last.
+    pending: Option<Entry<'e>>,
+    /// Level of the element the keys belong to, 0 for the output map.
+    depth: usize,
+    cfg: &'c Node2ObjectConfig,
+}
+
+impl<'de, 'e, 'c> MapAccess<'de> for EntriesAccess<'e, 'c> {
+    type Error = ConvertError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, ConvertError> {
+        match self.entries.next() {
+            Some((key, entry)) => {
+                self.pending = Some(entry);
+                seed.deserialize(IntoDeserializer::<ConvertError>::into_deserializer(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, ConvertError> {
+        match self.pending.take() {
+            Some(Entry::Scalar(v)) => seed.deserialize(v).map_err(json_error),
+            Some(Entry::List(first, items)) => seed.deserialize(ListDeserializer {
+                first,
+                items: items.into_iter(),
+                depth: self.depth + 1,
+                cfg: self.cfg,
+            }),
+            Some(Entry::Element(e)) => seed.deserialize(ElementDeserializer {
+                e,
+                depth: self.depth + 1,
+                cfg: self.cfg,
+            }),
+            None => Err(de::Error::custom("value requested before its key")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// The list of values under one key of a converted element.
+struct ListDeserializer<'e, 'c> {
+    first: Option<Value>,
+    items: vec::IntoIter<&'e treexml::Element>,
+    /// Level of the items.
+    depth: usize,
+    cfg: &'c Node2ObjectConfig,
+}
+
+impl<'de, 'e, 'c> de::Deserializer<'de> for ListDeserializer<'e, 'c> {
+    type Error = ConvertError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+        visitor.visit_seq(self)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V) -> Result<V::Value, ConvertError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, 'e, 'c> SeqAccess<'de> for ListDeserializer<'e, 'c> {
+    type Error = ConvertError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, ConvertError> {
+        if let Some(v) = self.first.take() {
+            return seed.deserialize(v).map(Some).map_err(json_error);
+        }
+        match self.items.next() {
+            Some(e) => seed
+                .deserialize(ElementDeserializer {
+                    e,
+                    depth: self.depth,
+                    cfg: self.cfg,
+                })
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.first.iter().count() + self.items.len())
+    }
+}
+
+/// Deserializes a `T` from `e` the way `serde_json::from_value` would deserialize it from
+/// `Value::Object(node2object(e))`, without building the JSON tree in between: the same keys,
+/// lists and text values, so documents that do not fit `T` fail the same way. Only the
+/// default settings are supported; see `from_element_with` for others. Deserialization
+/// errors are reported as `ConvertError::Deserialize`, with the path of the element they
+/// occurred in, and elements deeper than `max_depth` as `ConvertError::TooDeep`.
+pub fn from_element<T: DeserializeOwned>(e: &treexml::Element) -> Result<T, ConvertError> {
+    let cfg = Node2ObjectConfig::default();
+    let mut root = BTreeMap::new();
+    root.insert(root_key(e, &cfg), Entry::Element(e));
+    T::deserialize(RootDeserializer { entries: root, cfg: &cfg })
+}
+
+/// Deserializes a `T` from what `try_node2object` returns for `e` with `cfg`, so every
+/// setting applies, `key_case`, `key_remap` and `attribute_prefix` included. Unlike
+/// `from_element` this builds the JSON tree first. Conversion errors are returned as they
+/// are; deserialization errors as `ConvertError::Deserialize`, without a path.
+pub fn from_element_with<T: DeserializeOwned>(e: &treexml::Element, cfg: &Node2ObjectConfig) -> Result<T, ConvertError> {
+    let data = try_node2object(e, cfg)?;
+    T::deserialize(Value::Object(data)).map_err(json_error)
+}
+
+/// The output map of `node2object`, holding the top-level element.
+struct RootDeserializer<'e, 'c> {
+    entries: BTreeMap<String, Entry<'e>>,
+    cfg: &'c Node2ObjectConfig,
+}
+
+impl<'de, 'e, 'c> de::Deserializer<'de> for RootDeserializer<'e, 'c> {
+    type Error = ConvertError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+        visitor.visit_map(EntriesAccess {
+            entries: self.entries.into_iter(),
+            pending: None,
+            depth: 0,
+            cfg: self.cfg,
+        })
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+        visitor.visit_some(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use {node2object, KeyCase, DEFAULT_MAX_DEPTH};
+
+    /// `{"population": {"entry": [{"name": [...]}, ...]}}` and the like.
+    type Records<T> = HashMap<String, HashMap<String, Vec<HashMap<String, Vec<T>>>>>;
+
+    fn parse(xml: &str) -> treexml::Element {
+        treexml::Document::parse(xml.as_bytes()).unwrap().root.unwrap()
+    }
+
+    #[test]
+    fn matches_node2object() {
+        let fixtures = [
+            "<population><entry><name>Alex</name><height>173.5</height></entry>\
+             <entry><name>Mel</name><height>180.4</height></entry></population>",
+            r#"<item id="1" name="a"><id>2</id><option>x</option><empty/></item>"#,
+            r#"<p lang="en">hello <b>world</b> goodbye</p>"#,
+            r#"<player score="9000">Kolya</player>"#,
+            "<empty/>",
+        ];
+        for xml in &fixtures {
+            let e = parse(xml);
+            assert_eq!(from_element::<Value>(&e).unwrap(), Value::Object(node2object(&e)));
+        }
+    }
+
+    #[test]
+    fn typed() {
+        let e = parse("<population><entry><name>Alex</name></entry><entry><name>Mel</name></entry></population>");
+        let data: Records<String> = from_element(&e).unwrap();
+        assert_eq!(data["population"]["entry"][1]["name"], vec!["Mel".to_string()]);
+
+        let e = parse("<n>Alex</n>");
+        assert_eq!(from_element::<HashMap<String, Option<String>>>(&e).unwrap()["n"], Some("Alex".into()));
+
+        let e = parse("<population><entry><height>tall</height></entry></population>");
+        match from_element::<Records<f64>>(&e) {
+            Err(ConvertError::Deserialize { path, .. }) => assert_eq!(path, "population/entry/height"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn with_settings() {
+        let e = parse(r#"<Item id="1"><option>x</option></Item>"#);
+        let data: HashMap<String, Value> = from_element_with(&e, &Node2ObjectConfig::default()).unwrap();
+        assert_eq!(data["item"], json!({ "id": "1", "option_tag": ["x"] }));
+
+        let cfg = Node2ObjectConfig {
+            key_case: KeyCase::Verbatim,
+            attribute_prefix: "@".to_string(),
+            ..Node2ObjectConfig::default()
+        };
+        let data: HashMap<String, Value> = from_element_with(&e, &cfg).unwrap();
+        assert_eq!(data["Item"], json!({ "@id": "1", "option": ["x"] }));
+
+        let cfg = Node2ObjectConfig {
+            max_depth: Some(1),
+            ..Node2ObjectConfig::default()
+        };
+        assert_eq!(
+            from_element_with::<Value>(&e, &cfg),
+            Err(ConvertError::TooDeep { path: "Item/option".into(), max: 1 })
+        );
+    }
+
+    #[test]
+    fn too_deep() {
+        let mut chain = treexml::Element::new("a");
+        for _ in 1..50_000 {
+            let mut parent = treexml::Element::new("a");
+            parent.children.push(chain);
+            chain = parent;
+        }
+
+        let path = vec!["a"; DEFAULT_MAX_DEPTH + 1].join("/");
+        assert_eq!(
+            from_element::<Value>(&chain),
+            Err(ConvertError::TooDeep { path, max: DEFAULT_MAX_DEPTH })
+        );
+
+        let mut pending = vec![chain];
+        while let Some(mut e) = pending.pop() {
+            pending.append(&mut e.children);
+        }
+    }
+}
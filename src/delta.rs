@@ -0,0 +1,73 @@
+//! Change tracking between conversions of an evolving document.
+
+use serde_json::{Map, Value};
+use treexml;
+
+use {node2object_with, Node2ObjectConfig};
+
+/// JSON merge patch (RFC 7386) turning `prev` into `next`: keys that changed map to their new
+/// value, removed keys map to `null` and unchanged keys are left out. Objects are compared key
+/// by key; anything else, arrays included, is replaced as a whole.
+fn diff(prev: &Value, next: &Value) -> Option<Value> {
+    match (prev, next) {
+        (Value::Object(prev), Value::Object(next)) => {
+            let mut patch = Map::new();
+            for key in prev.keys() {
+                if !next.contains_key(key) {
+                    patch.insert(key.clone(), Value::Null);
+                }
+            }
+            for (key, value) in next {
+                match prev.get(key) {
+                    Some(old) => {
+                        if let Some(change) = diff(old, value) {
+                            patch.insert(key.clone(), change);
+                        }
+                    }
+                    None => {
+                        patch.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            if patch.is_empty() {
+                None
+            } else {
+                Some(Value::Object(patch))
+            }
+        }
+        (prev, next) if prev == next => None,
+        (_, next) => Some(next.clone()),
+    }
+}
+
+/// Converts `e` and returns what changed relative to `prev`, an earlier conversion of the same
+/// document, as a JSON merge patch: an empty object if nothing changed. Merge patches cannot
+/// tell a `null` value from a removed key, so values that became `null` read as removed.
+pub fn convert_delta(prev: &Value, e: &treexml::Element, cfg: &Node2ObjectConfig) -> Value {
+    let next = Value::Object(node2object_with(e, cfg));
+    diff(prev, &next).unwrap_or_else(|| Value::Object(Map::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_and_single_field_change() {
+        let cfg = Node2ObjectConfig::default();
+        let before = treexml::Document::parse(r#"<entry id="7">
+            <name>Alex</name>
+            <height>173.5</height>
+            </entry>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let after = treexml::Document::parse(r#"<entry id="7">
+            <name>Alex</name>
+            <height>174</height>
+            </entry>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let prev = Value::Object(node2object_with(&before, &cfg));
+
+        assert_eq!(convert_delta(&prev, &before, &cfg), json!({}));
+        assert_eq!(convert_delta(&prev, &after, &cfg), json!({ "entry": { "height": ["174"] } }));
+    }
+}
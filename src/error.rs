@@ -0,0 +1,129 @@
+//! Errors reported by `try_node2object`.
+
+use std::error::Error;
+use std::fmt;
+
+use serde::de;
+
+/// Reason a tree was rejected during conversion. `path` is the slash-separated list of
+/// element names from the root down to the offending element, e.g. `html/head/link`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConvertError {
+    /// An element listed in `required_attributes` lacks one of its attributes.
+    MissingAttribute { path: String, attribute: String },
+    /// A key from `key_aliases` clashes with another key of the same object.
+    KeyCollision { path: String, key: String },
+    /// A text or attribute value is longer than `max_string_len` bytes.
+    StringTooLong { path: String, len: usize, max: usize },
+    /// A `timeseries` sample lacks one of the listed fields.
+    MissingField { path: String, field: String },
+    /// An element or attribute name is not a valid XML name (`strict_wellformed`).
+    InvalidName { path: String, name: String },
+    /// Two attributes of the same element produce the same key (`strict_wellformed`).
+    DuplicateAttribute { path: String, attribute: String },
+    /// The strings of the document add up to more than `max_total_string_bytes` bytes.
+    StringBudgetExceeded { path: String, max: usize },
+    /// An element listed in `value_attribute` also has text or children.
+    ValueAttributeConflict { path: String, attribute: String },
+    /// Several `keyed_value` children share a key under `KeyedDuplicatePolicy::Error`.
+    DuplicateKey { path: String, key: String },
+    /// A number has a fractional part under `NumericOutput::AllInt` and
+    /// `FractionalIntPolicy::Error`. Here `path` lists the output keys and indexes leading
+    /// to the number, e.g. `r/x/0`.
+    FractionalNumber { path: String, value: String },
+    /// The document has no root element under `EmptyDocument::Error`. `path` is empty.
+    EmptyDocument { path: String },
+    /// An element is nested deeper than `max_depth`.
+    TooDeep { path: String, max: usize },
+    /// An attribute and a child element produce the same key under
+    /// `AttributeChildCollision::Error`.
+    AttributeCollision { path: String, key: String },
+    /// `from_element` could not deserialize the document into the requested type. `path` is
+    /// empty if the error concerns the output map itself.
+    Deserialize { path: String, message: String },
+}
+
+impl ConvertError {
+    /// Slash-separated path of the offending element (output keys for `FractionalNumber`,
+    /// empty for `EmptyDocument`).
+    pub fn path(&self) -> &str {
+        match *self {
+            ConvertError::MissingAttribute { ref path, .. }
+            | ConvertError::KeyCollision { ref path, .. }
+            | ConvertError::StringTooLong { ref path, .. }
+            | ConvertError::MissingField { ref path, .. }
+            | ConvertError::InvalidName { ref path, .. }
+            | ConvertError::DuplicateAttribute { ref path, .. }
+            | ConvertError::StringBudgetExceeded { ref path, .. }
+            | ConvertError::ValueAttributeConflict { ref path, .. }
+            | ConvertError::DuplicateKey { ref path, .. }
+            | ConvertError::FractionalNumber { ref path, .. }
+            | ConvertError::EmptyDocument { ref path }
+            | ConvertError::TooDeep { ref path, .. }
+            | ConvertError::AttributeCollision { ref path, .. }
+            | ConvertError::Deserialize { ref path, .. } => path,
+        }
+    }
+
+    /// Name of the offending element, the last segment of `path`.
+    pub fn element(&self) -> &str {
+        self.path().rsplit('/').next().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConvertError::MissingAttribute { ref path, ref attribute } => {
+                write!(f, "{}: missing required attribute `{}`", path, attribute)
+            }
+            ConvertError::KeyCollision { ref path, ref key } => {
+                write!(f, "{}: aliased key `{}` collides with an existing key", path, key)
+            }
+            ConvertError::StringTooLong { ref path, len, max } => {
+                write!(f, "{}: string of {} bytes exceeds the limit of {}", path, len, max)
+            }
+            ConvertError::MissingField { ref path, ref field } => {
+                write!(f, "{}: sample is missing field `{}`", path, field)
+            }
+            ConvertError::InvalidName { ref path, ref name } => {
+                write!(f, "{}: `{}` is not a valid XML name", path, name)
+            }
+            ConvertError::DuplicateAttribute { ref path, ref attribute } => {
+                write!(f, "{}: attribute `{}` duplicates another attribute", path, attribute)
+            }
+            ConvertError::StringBudgetExceeded { ref path, max } => {
+                write!(f, "{}: strings exceed the total limit of {} bytes", path, max)
+            }
+            ConvertError::ValueAttributeConflict { ref path, ref attribute } => {
+                write!(f, "{}: element with value attribute `{}` also has content", path, attribute)
+            }
+            ConvertError::DuplicateKey { ref path, ref key } => {
+                write!(f, "{}: keyed entry `{}` occurs more than once", path, key)
+            }
+            ConvertError::FractionalNumber { ref path, ref value } => {
+                write!(f, "{}: number {} is not an integer", path, value)
+            }
+            ConvertError::EmptyDocument { .. } => write!(f, "document has no root element"),
+            ConvertError::TooDeep { ref path, max } => {
+                write!(f, "{}: element is nested deeper than {} levels", path, max)
+            }
+            ConvertError::AttributeCollision { ref path, ref key } => {
+                write!(f, "{}: attribute and child element share the key `{}`", path, key)
+            }
+            ConvertError::Deserialize { ref path, ref message } if path.is_empty() => f.write_str(message),
+            ConvertError::Deserialize { ref path, ref message } => write!(f, "{}: {}", path, message),
+        }
+    }
+}
+
+impl Error for ConvertError {}
+
+impl de::Error for ConvertError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ConvertError::Deserialize {
+            path: String::new(),
+            message: msg.to_string(),
+        }
+    }
+}
@@ -23,7 +23,12 @@
 //!         </population>
 //!     ".as_bytes()).unwrap().root.unwrap();
 //!
-//!     assert_eq!(serde_json::Value::Object(node2object::node2object(&dom_root)), json!(
+//!     let cfg = node2object::Node2ObjectConfig {
+//!         coerce_numbers: true,
+//!         collapse_single_element_arrays: true,
+//!         ..node2object::Node2ObjectConfig::default()
+//!     };
+//!     assert_eq!(serde_json::Value::Object(node2object::node2object_with(&dom_root, &cfg)), json!(
 //!         {
 //!           "population": {
 //!             "entry": [
@@ -35,172 +40,2886 @@
 //!     ));
 //! }
 //! ```
+//!
+//! ## Memory
+//! Every string of the result is its own allocation, even when the same value occurs
+//! thousands of times: `serde_json::Value` owns its strings, so they cannot share storage
+//! with each other or with the `treexml` tree, which owns its strings as well. Drop the
+//! tree once it is converted if peak memory matters.
+
+#![cfg_attr(test, feature(test))]
+
+extern crate treexml;
+extern crate inflector;
+#[macro_use]
+extern crate serde;
+
+#[cfg_attr(test, macro_use)]
+extern crate serde_json;
+#[cfg(test)]
+extern crate test;
+
+mod config;
+mod de;
+mod delta;
+mod error;
+mod number;
+mod query;
+mod records;
+mod reverse;
+mod schema;
+mod subtree;
+mod summary;
+mod typescript;
+#[cfg(feature = "csv")]
+mod csv;
+
+pub use config::{
+    AmbiguousNumberFn, AttributeChildCollision, DedupMode, EmptyDetection, EmptyDocument, EmptyRepr,
+    FractionalIntPolicy, JsonType, KeyCase, KeyedDuplicatePolicy, KeyFn, NamespaceMode, Node2ObjectConfig,
+    NonFiniteNumbers, NumericOutput, ProgressFn, RenameFn, StringLimitPolicy, TextWhitespace, ValueAttributeConflict,
+    ValueOverrideFn, ATTRIBUTE_LIST_KEY, DEFAULT_MAX_DEPTH, DEFAULT_TEXT_KEY,
+};
+pub use de::{from_element, from_element_with};
+pub use delta::convert_delta;
+pub use error::ConvertError;
+pub use number::{
+    ArbitraryPrecision, DefaultNumberParser, IntegerPreferring, NumberParser, NumberRisk, ParsedNumber, StringsOnly,
+};
+pub use query::XmlJson;
+pub use records::{convert_to_channel, convert_to_channel_with, extract_records, partition, to_ndjson};
+pub use reverse::{object2node, object2node_with};
+pub use schema::infer_json_schema;
+pub use subtree::reconvert_subtree;
+pub use summary::summarize;
+pub use typescript::to_typescript_interface;
+#[cfg(feature = "csv")]
+pub use csv::to_csv;
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+
+use serde_json::{Map, Number, Value};
+use inflector::cases::snakecase::to_snake_case;
+
+/// Shape of an element, which decides how it is converted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XMLNodeType {
+    /// No text, attributes or children.
+    Empty,
+    /// Text only; converted to a scalar.
+    Text,
+    /// Attributes only; converted to an object.
+    Attributes,
+    /// Text and attributes; converted to an object with the text under `text_key`.
+    TextAndAttributes,
+    /// Child elements and possibly attributes, but no text.
+    Parent,
+    /// Child elements mixed with text; converted like `Parent`, with the text (all of its
+    /// segments joined together) under `text_key`.
+    SemiStructured,
+}
+
+#[allow(clippy::collapsible_else_if)]
+fn scan_xml_node(e: &treexml::Element, detection: EmptyDetection) -> XMLNodeType {
+    let has_content = |s: &Option<String>| match (s, detection) {
+        (Some(s), EmptyDetection::IgnoreWhitespace) => !s.trim().is_empty(),
+        (s, _) => s.is_some(),
+    };
+    let has_text = has_content(&e.text) || has_content(&e.cdata);
+    if e.children.is_empty() {
+        if !has_text {
+            if e.attributes.is_empty() {
+                XMLNodeType::Empty
+            } else {
+                XMLNodeType::Attributes
+            }
+        } else {
+            if e.attributes.is_empty() {
+                XMLNodeType::Text
+            } else {
+                XMLNodeType::TextAndAttributes
+            }
+        }
+    } else {
+        if has_text {
+            XMLNodeType::SemiStructured
+        } else {
+            XMLNodeType::Parent
+        }
+    }
+}
+
+fn convert_key(name: &str, cfg: &Node2ObjectConfig) -> String {
+    let name = match name.find(':') {
+        Some(i) if cfg.strip_prefixes.contains(&name[..i]) => &name[i + 1..],
+        _ => name,
+    };
+    match cfg.key_case {
+        KeyCase::SnakeCase => snake_case(name),
+        KeyCase::Verbatim => name.to_string(),
+        KeyCase::Custom(ref convert) => convert(name),
+    }
+}
+
+/// Output key of the attribute `name`, keeping its namespace prefix verbatim unless
+/// `namespace_mode` is `Strip` or the prefix is listed in `strip_prefixes`.
+fn attribute_name_key(name: &str, cfg: &Node2ObjectConfig) -> String {
+    match name.find(':') {
+        Some(i) if cfg.namespace_mode != NamespaceMode::Strip && !cfg.strip_prefixes.contains(&name[..i]) => {
+            format!("{}:{}", &name[..i], convert_key(&name[i + 1..], cfg))
+        }
+        _ => convert_key(name, cfg),
+    }
+}
+
+/// Output key of the attribute `name`, carrying `attribute_prefix`.
+fn attribute_key(name: &str, cfg: &Node2ObjectConfig) -> String {
+    let key = attribute_name_key(name, cfg);
+    if cfg.attribute_prefix.is_empty() {
+        key
+    } else {
+        format!("{}{}", cfg.attribute_prefix, key)
+    }
+}
+
+/// Upper bound on the names remembered by `snake_case` per thread.
+const SNAKE_CASE_CACHE_LIMIT: usize = 1024;
+
+/// Longest name, in bytes, that `snake_case` remembers. Together with the entry limit this
+/// keeps the cache of one thread under about 256KB whatever the documents contain.
+const SNAKE_CASE_CACHE_MAX_NAME: usize = 64;
+
+thread_local! {
+    /// Names converted by `snake_case` on this thread, with their snake_case form.
+    static SNAKE_CASE_CACHE: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+/// `to_snake_case`, remembering recent results on the current thread. Documents repeat the
+/// same few names over and over, and `to_snake_case` builds several temporary strings per
+/// call, so looking a name up first leaves only the copy of the result to allocate; the output
+/// map owns its keys, so that copy is needed anyway. The cache is per thread, forgets
+/// everything once it holds `SNAKE_CASE_CACHE_LIMIT` names, skips names longer than
+/// `SNAKE_CASE_CACHE_MAX_NAME`, and is transparent to callers.
+fn snake_case(name: &str) -> String {
+    SNAKE_CASE_CACHE.with(|cache| {
+        if let Some(key) = cache.borrow().get(name) {
+            return key.clone();
+        }
+        let key = to_snake_case(name);
+        if name.len() > SNAKE_CASE_CACHE_MAX_NAME {
+            return key;
+        }
+        let mut cache = cache.borrow_mut();
+        if cache.len() >= SNAKE_CASE_CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert(name.to_string(), key.clone());
+        key
+    })
+}
+
+/// Output key of element `e` named `name` (after `pre_rename`).
+fn element_key(e: &treexml::Element, name: &str, cfg: &Node2ObjectConfig) -> String {
+    prefixed_key(e, local_key(name, cfg), cfg)
+}
+
+/// `key` with the namespace prefix of `e` in front, if `namespace_mode` keeps prefixes.
+fn prefixed_key(e: &treexml::Element, key: String, cfg: &Node2ObjectConfig) -> String {
+    match (cfg.namespace_mode, &e.prefix) {
+        (NamespaceMode::Strip, _) | (_, None) => key,
+        (_, Some(prefix)) => format!("{}:{}", prefix, key),
+    }
+}
+
+/// Output key for the local name of an element.
+fn local_key(name: &str, cfg: &Node2ObjectConfig) -> String {
+    if let Some(alias) = cfg.key_aliases.get(name) {
+        return alias.clone();
+    }
+    let name = match type_suffix(name, cfg) {
+        Some((suffix, _)) if cfg.strip_type_suffix => &name[..name.len() - suffix.len()],
+        _ => name,
+    };
+    let key = convert_key(name, cfg);
+    match (&cfg.key_case, cfg.key_remap.get(&key)) {
+        (KeyCase::SnakeCase, Some(remapped)) => remapped.clone(),
+        _ => key,
+    }
+}
+
+/// Longest entry of `type_suffix_map` that `tag` ends with (and is longer than).
+fn type_suffix<'c>(tag: &str, cfg: &'c Node2ObjectConfig) -> Option<(&'c str, JsonType)> {
+    cfg.type_suffix_map
+        .iter()
+        .filter(|&(suffix, _)| tag.len() > suffix.len() && tag.ends_with(suffix.as_str()))
+        .max_by_key(|&(suffix, _)| suffix.len())
+        .map(|(suffix, t)| (suffix.as_str(), *t))
+}
+
+/// Converts `text` to the requested type, keeping it as a string if it does not parse.
+fn parse_typed(text: &str, t: JsonType) -> Value {
+    let parsed = match t {
+        JsonType::String => None,
+        JsonType::Integer => text.trim().parse::<i64>().ok().map(|v| Value::Number(v.into())),
+        JsonType::Float => text.trim().parse::<f64>().ok().and_then(Number::from_f64).map(Value::Number),
+        JsonType::Bool => text.trim().parse::<bool>().ok().map(Value::Bool),
+    };
+    parsed.unwrap_or_else(|| Value::String(text.into()))
+}
+
+/// Rewrites `text` into the form `f64::from_str` expects, according to the configured
+/// separators. Thousands separators are only honored for tags listed in `coerce_only`, and
+/// only when every group after the first has exactly three digits.
+fn normalize_number(text: &str, tag: &str, cfg: &Node2ObjectConfig) -> Option<String> {
+    let scoped = cfg.coerce_only.as_ref().is_some_and(|tags| tags.contains(tag));
+    let (int_part, frac_part) = match text.find(cfg.decimal_separator) {
+        Some(i) => (&text[..i], Some(&text[i + cfg.decimal_separator.len_utf8()..])),
+        None => (text, None),
+    };
+    let int_part = match cfg.thousands_separator {
+        Some(sep) if scoped && int_part.contains(sep) => {
+            let digits = int_part.trim_start_matches(['-', '+']);
+            let mut groups = digits.split(sep);
+            let first = groups.next().unwrap_or_default();
+            if first.is_empty() || first.len() > 3 || groups.any(|g| g.len() != 3) {
+                return None;
+            }
+            int_part.replace(sep, "")
+        }
+        _ => int_part.to_string(),
+    };
+    Some(match frac_part {
+        Some(frac) => format!("{}.{}", int_part, frac),
+        None => int_part,
+    })
+}
+
+fn is_time_shape(shape: &str) -> bool {
+    let whole = match shape.find('.') {
+        Some(i) if shape.len() > i + 1 && shape[i + 1..].chars().all(|c| c == '9') => &shape[..i],
+        Some(_) => return false,
+        None => shape,
+    };
+    whole == "99:99" || whole == "99:99:99"
+}
+
+/// Whether `text` looks like an ISO 8601 / XSD date or time: `2020`, `2020-05`, `2020-05-01`,
+/// `--05`, `--05-01`, `---01`, `12:30`, `12:30:00.5`, or a date and time joined by `T`, each
+/// optionally followed by `Z` or a `+hh:mm`/`-hh:mm` offset.
+fn is_date_like(text: &str) -> bool {
+    if !text.is_ascii() {
+        return false;
+    }
+    let shape: String = text.trim().chars().map(|c| if c.is_ascii_digit() { '9' } else { c }).collect();
+    let has_zone = shape.len() > 6 && {
+        let zone = &shape[shape.len() - 6..];
+        (zone.starts_with('+') || zone.starts_with('-')) && &zone[1..] == "99:99"
+    };
+    let shape = if has_zone {
+        &shape[..shape.len() - 6]
+    } else {
+        shape.trim_end_matches('Z')
+    };
+    let is_date = |d: &str| ["9999", "9999-99", "9999-99-99", "--99", "--99-99", "---99"].contains(&d);
+    match shape.find('T') {
+        Some(i) => is_date(&shape[..i]) && is_time_shape(&shape[i + 1..]),
+        None => is_date(shape) || is_time_shape(shape),
+    }
+}
+
+/// Text that `parse_number` would coerce, normalized by the separator settings.
+fn numeric_text(text: &str, tag: &str, cfg: &Node2ObjectConfig) -> Option<String> {
+    if !cfg.coerce_numbers || cfg.coerce_only.as_ref().is_some_and(|tags| !tags.contains(tag)) {
+        return None;
+    }
+    if cfg.preserve_date_like && is_date_like(text) {
+        return None;
+    }
+    normalize_number(text, tag, cfg)
+}
+
+/// Whether `text` is `NaN` or an infinity that `coerce_numbers` applies to.
+fn is_non_finite(text: &str, tag: &str, cfg: &Node2ObjectConfig) -> bool {
+    numeric_text(text, tag, cfg).and_then(|t| t.parse::<f64>().ok()).is_some_and(|n| !n.is_finite())
+}
+
+fn parse_number(text: &str, tag: &str, cfg: &Node2ObjectConfig) -> Option<Value> {
+    let normalized = numeric_text(text, tag, cfg)?;
+    if normalized.parse::<f64>().is_ok_and(|n| !n.is_finite()) {
+        return None;
+    }
+    let number = cfg.number_parser.parse(&normalized)?;
+    if cfg.canonical_numbers_only && !number_survives(&number, &normalized) {
+        return None;
+    }
+    Some(number)
+}
+
+fn is_null_token(text: &str, cfg: &Node2ObjectConfig) -> bool {
+    if cfg.null_tokens_ignore_case {
+        cfg.null_tokens.iter().any(|t| t.eq_ignore_ascii_case(text))
+    } else {
+        cfg.null_tokens.contains(text)
+    }
+}
+
+/// Yes/no words of the locales supported by `bool_locale`.
+fn locale_bool_words(locale: &str) -> &'static [(&'static str, bool)] {
+    let language = locale.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+    match language.as_str() {
+        "fr" => &[("oui", true), ("non", false)],
+        "de" => &[("ja", true), ("nein", false)],
+        "ru" => &[("да", true), ("нет", false)],
+        "es" => &[("sí", true), ("si", true), ("no", false)],
+        _ => &[],
+    }
+}
+
+/// Boolean meant by `text` according to `bool_tokens` and `bool_locale`, ignoring case.
+fn bool_token(text: &str, cfg: &Node2ObjectConfig) -> Option<bool> {
+    if cfg.bool_tokens.is_empty() && cfg.bool_locale.is_none() {
+        return None;
+    }
+    let text = text.trim().to_lowercase();
+    let locale = cfg.bool_locale.as_ref().map_or(&[][..], |l| locale_bool_words(l));
+    cfg.bool_tokens
+        .iter()
+        .find(|&(token, _)| token.to_lowercase() == text)
+        .map(|(_, &b)| b)
+        .or_else(|| locale.iter().find(|&&(word, _)| word == text).map(|&(_, b)| b))
+}
+
+/// Applies `value_replacements` to a converted scalar.
+fn replace_value(v: Value, cfg: &Node2ObjectConfig) -> Value {
+    match v {
+        Value::String(s) => match cfg.value_replacements.get(&s) {
+            Some(replacement) => replacement.clone(),
+            None => Value::String(s),
+        },
+        v => v,
+    }
+}
+
+/// Whether coercing `text` involved a judgment call: a number kept as a string because it
+/// looks like a date, or a number whose text does not survive the round trip (`007`, `+5`).
+fn is_ambiguous(text: &str, is_number: bool, tag: &str, cfg: &Node2ObjectConfig) -> bool {
+    if !cfg.coerce_numbers {
+        return false;
+    }
+    if is_number {
+        let digits = text.trim_start_matches('-');
+        text.starts_with('+') || (digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0."))
+    } else {
+        cfg.preserve_date_like
+            && is_date_like(text)
+            && normalize_number(text, tag, cfg).is_some_and(|t| t.parse::<f64>().is_ok())
+    }
+}
+
+/// Digits beyond which an integer no longer fits an `f64` mantissa exactly.
+const MAX_EXACT_DIGITS: usize = 15;
+
+/// Why the number parsed from `text` might have been meant as a string, if at all. A bare
+/// year such as `1962` is an ordinary integer here; only text with date separators or a
+/// zone counts as date-like.
+fn number_risk(text: &str) -> Option<NumberRisk> {
+    let text = text.trim();
+    let digits = text.trim_start_matches(['-', '+']);
+    if is_date_like(text) && text.contains(['-', ':', 'T', 'Z']) {
+        Some(NumberRisk::DateLike)
+    } else if text.contains(['e', 'E']) {
+        Some(NumberRisk::Scientific)
+    } else if digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.") {
+        Some(NumberRisk::LeadingZero)
+    } else if digits.chars().filter(char::is_ascii_digit).count() > MAX_EXACT_DIGITS {
+        Some(NumberRisk::Long)
+    } else {
+        None
+    }
+}
+
+/// Value of `text` from the first type of `coercion_chain` it converts to.
+fn parse_chain(text: &str, tag: &str, chain: &[JsonType], cfg: &Node2ObjectConfig) -> Value {
+    let scoped = cfg.coerce_only.as_ref().is_none_or(|tags| tags.contains(tag));
+    let numeric = scoped && !(cfg.preserve_date_like && is_date_like(text));
+    let number = || normalize_number(text.trim(), tag, cfg);
+    for t in chain {
+        let value = match *t {
+            JsonType::String => break,
+            JsonType::Bool if scoped => bool_token(text, cfg).or(match text.trim() {
+                "true" | "1" => Some(true),
+                "false" | "0" => Some(false),
+                _ => None,
+            }).map(Value::Bool),
+            JsonType::Integer if numeric => number()
+                .and_then(|n| n.parse::<i64>().ok())
+                .map(|n| Value::Number(n.into())),
+            JsonType::Float if numeric => number()
+                .and_then(|n| n.parse::<f64>().ok())
+                .and_then(Number::from_f64)
+                .map(Value::Number),
+            _ => None,
+        };
+        if let Some(value) = value {
+            return value;
+        }
+    }
+    replace_value(Value::String(text.into()), cfg)
+}
+
+fn parse_text(text: &str, tag: &str, cfg: &Node2ObjectConfig) -> Value {
+    if is_null_token(text, cfg) {
+        return Value::Null;
+    }
+    if let Some(ref chain) = cfg.coercion_chain {
+        return parse_chain(text, tag, chain, cfg);
+    }
+    if let Some(b) = bool_token(text, cfg) {
+        return Value::Bool(b);
+    }
+    if cfg.non_finite_numbers == NonFiniteNumbers::Null && is_non_finite(text, tag, cfg) {
+        return Value::Null;
+    }
+    let number = parse_number(text, tag, cfg);
+    if let (Some(value), Some(decide)) = (&number, &cfg.on_ambiguous_number) {
+        if let Some(risk) = number_risk(text) {
+            let value = value.clone();
+            return decide(text, ParsedNumber { value, risk });
+        }
+    }
+    let is_number = number.is_some();
+    let value = match number {
+        Some(v) if cfg.dual_number => {
+            let mut dual = Map::new();
+            dual.insert(NUM_KEY.to_string(), v);
+            dual.insert(RAW_KEY.to_string(), Value::String(text.into()));
+            Value::Object(dual)
+        }
+        Some(ref v) if cfg.preserve_number_format && !number_survives(v, text) => {
+            let mut raw = Map::new();
+            raw.insert(RAW_KEY.to_string(), Value::String(text.into()));
+            Value::Object(raw)
+        }
+        Some(v) => v,
+        None => replace_value(Value::String(text.into()), cfg),
+    };
+    if cfg.raw_fallback && is_ambiguous(text, is_number, tag, cfg) {
+        let mut annotated = Map::new();
+        annotated.insert("value".to_string(), value);
+        annotated.insert("#raw".to_string(), Value::String(text.into()));
+        Value::Object(annotated)
+    } else {
+        value
+    }
+}
+
+/// Whether `number` is written back exactly as `text`.
+fn number_survives(number: &Value, text: &str) -> bool {
+    let written = number.to_string();
+    written == text
+}
+
+/// Key of the parsed number in the objects written by `dual_number`.
+const NUM_KEY: &str = "$num";
+/// Key of a number's source text in the objects written by `dual_number` and
+/// `preserve_number_format`.
+const RAW_KEY: &str = "$raw";
+
+fn parse_attribute(tag: &str, name: &str, value: &str, cfg: &Node2ObjectConfig) -> Value {
+    if cfg.presence_attributes && (value.is_empty() || value.eq_ignore_ascii_case(name)) {
+        Value::Bool(true)
+    } else if cfg.coerce_attributes {
+        parse_text(value, tag, cfg)
+    } else {
+        Value::String(value.into())
+    }
+}
+
+fn empty_value(cfg: &Node2ObjectConfig) -> Option<Value> {
+    match cfg.empty_element {
+        EmptyRepr::Null | EmptyRepr::Omit => None,
+        EmptyRepr::EmptyString => Some(Value::String(String::new())),
+        EmptyRepr::EmptyObject => Some(Value::Object(Map::new())),
+    }
+}
+
+/// Attribute naming an element for `resolve_idrefs`.
+const ID_ATTRIBUTE: &str = "id";
+/// Attribute referring to another element for `resolve_idrefs`.
+const IDREF_ATTRIBUTE: &str = "idref";
+/// Key holding the referenced element under `resolve_idrefs`.
+const REF_KEY: &str = "#ref";
+/// Key holding the CDATA of an element under `preserve_cdata`.
+const CDATA_KEY: &str = "#cdata";
+/// Appended to a list's key for the key holding its length under `annotate_counts`.
+const COUNT_SUFFIX: &str = "#count";
+/// Key of the placeholder standing in for an element deeper than `max_depth`.
+const TRUNCATED_KEY: &str = "#truncated";
+
+/// State threaded through a single conversion.
+struct Context<'a, 'e> {
+    cfg: &'a Node2ObjectConfig,
+    /// Whether settings that reject documents are enforced (only through `try_node2object`).
+    fallible: bool,
+    /// Names of the elements from the root down to the one being converted.
+    path: Vec<String>,
+    /// JSON pointer tokens of the value being converted; only kept while recording node types.
+    pointer: Vec<String>,
+    /// Node type of every converted element, keyed by JSON pointer, if requested.
+    node_types: Option<HashMap<String, XMLNodeType>>,
+    /// Number of elements visited so far, for `on_progress`.
+    visited: usize,
+    /// Elements by `id` attribute, for `resolve_idrefs`.
+    ids: HashMap<&'e str, &'e treexml::Element>,
+    /// Ids of the referenced elements inlined so far, including those being inlined.
+    inlined: HashSet<&'e str>,
+    /// Bytes of keys and values taken from the document so far, for `max_total_string_bytes`.
+    string_bytes: Cell<usize>,
+}
+
+impl<'a, 'e> Context<'a, 'e> {
+    fn new(cfg: &'a Node2ObjectConfig, fallible: bool) -> Self {
+        Context {
+            cfg,
+            fallible,
+            path: Vec::new(),
+            pointer: Vec::new(),
+            node_types: None,
+            visited: 0,
+            ids: HashMap::new(),
+            inlined: HashSet::new(),
+            string_bytes: Cell::new(0),
+        }
+    }
+
+    /// Indexes the elements of the tree under `root` by `id`, if `resolve_idrefs` is set.
+    /// The first element wins if an id is used twice.
+    fn index_ids(&mut self, root: &'e treexml::Element) {
+        if !self.cfg.resolve_idrefs {
+            return;
+        }
+        let mut pending = vec![root];
+        while let Some(e) = pending.pop() {
+            if let Some(id) = e.attributes.get(ID_ATTRIBUTE) {
+                self.ids.entry(id.as_str()).or_insert(e);
+            }
+            pending.extend(e.children.iter().rev());
+        }
+    }
+
+    fn path(&self) -> String {
+        self.path.join("/")
+    }
+
+    /// Tag name of the element being converted, after `pre_rename`.
+    fn tag(&self) -> &str {
+        self.path.last().map_or("", String::as_str)
+    }
+
+    /// Counts `s` towards `max_total_string_bytes`, failing once the total exceeds it.
+    fn charge(&self, s: &str) -> Result<(), ConvertError> {
+        let max = match self.cfg.max_total_string_bytes {
+            Some(max) if self.fallible => max,
+            _ => return Ok(()),
+        };
+        let total = self.string_bytes.get() + s.len();
+        self.string_bytes.set(total);
+        if total > max {
+            Err(ConvertError::StringBudgetExceeded { path: self.path(), max })
+        } else {
+            Ok(())
+        }
+    }
+
+    fn pointer(&self) -> String {
+        self.pointer
+            .iter()
+            .map(|t| format!("/{}", t.replace('~', "~0").replace('/', "~1")))
+            .collect()
+    }
+}
+
+/// Applies `max_string_len` to a text or attribute value, and counts the result towards
+/// `max_total_string_bytes`.
+fn limit_string<'t>(text: &'t str, ctx: &Context) -> Result<Cow<'t, str>, ConvertError> {
+    let text = truncate_string(text, ctx)?;
+    ctx.charge(&text)?;
+    Ok(text)
+}
+
+fn truncate_string<'t>(text: &'t str, ctx: &Context) -> Result<Cow<'t, str>, ConvertError> {
+    let max = match ctx.cfg.max_string_len {
+        Some(max) if text.len() > max => max,
+        _ => return Ok(Cow::Borrowed(text)),
+    };
+    if ctx.fallible && ctx.cfg.max_string_policy == StringLimitPolicy::Error {
+        return Err(ConvertError::StringTooLong {
+            path: ctx.path(),
+            len: text.len(),
+            max,
+        });
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Ok(Cow::Owned(format!("{}{}", &text[..end], ctx.cfg.truncation_marker)))
+}
+
+/// `attribute_defaults` entries for attributes that `e` lacks.
+fn missing_defaults<'c>(e: &treexml::Element, tag: &str, cfg: &'c Node2ObjectConfig) -> Vec<(&'c String, &'c Value)> {
+    cfg.attribute_defaults.get(tag).map_or_else(Vec::new, |defaults| {
+        defaults.iter().filter(|&(name, _)| !e.attributes.contains_key(name)).collect()
+    })
+}
+
+fn convert_attributes(e: &treexml::Element, ctx: &Context) -> Result<Map<String, Value>, ConvertError> {
+    let cfg = ctx.cfg;
+    let mut data = Map::new();
+    let defaults = missing_defaults(e, ctx.tag(), cfg);
+    if cfg.attributes_as_list {
+        let mut names: Vec<&String> = e.attributes.keys().chain(defaults.iter().map(|&(name, _)| name)).collect();
+        names.sort();
+        let mut list = Vec::with_capacity(names.len());
+        for k in names {
+            let value = match e.attributes.get(k) {
+                Some(v) => parse_attribute(ctx.tag(), k, &limit_string(v, ctx)?, cfg),
+                None => cfg.attribute_defaults[ctx.tag()][k].clone(),
+            };
+            let key = attribute_name_key(k, cfg);
+            ctx.charge(&key)?;
+            let mut entry = Map::new();
+            entry.insert("name".to_string(), Value::String(key));
+            entry.insert("value".to_string(), value);
+            list.push(Value::Object(entry));
+        }
+        if !list.is_empty() {
+            data.insert(ATTRIBUTE_LIST_KEY.to_string(), Value::Array(list));
+        }
+        return Ok(data);
+    }
+    for (k, v) in &e.attributes {
+        let v = limit_string(v, ctx)?;
+        let key = attribute_key(k, cfg);
+        ctx.charge(&key)?;
+        data.insert(key, parse_attribute(ctx.tag(), k, &v, cfg));
+    }
+    for (k, v) in defaults {
+        let key = attribute_key(k, cfg);
+        ctx.charge(&key)?;
+        data.insert(key, v.clone());
+    }
+    Ok(data)
+}
+
+/// Text of `e` followed by its CDATA, borrowed from the element unless it has both.
+fn element_text<'e>(e: &'e treexml::Element, separator: &str) -> Cow<'e, str> {
+    match (&e.text, &e.cdata) {
+        (Some(text), Some(cdata)) => Cow::Owned(format!("{}{}{}", text, separator, cdata)),
+        (Some(s), None) | (None, Some(s)) => Cow::Borrowed(s),
+        (None, None) => Cow::Borrowed(""),
+    }
+}
+
+fn parse_text_contents(e: &treexml::Element, ctx: &Context) -> Result<Value, ConvertError> {
+    let separator = ctx.cfg.text_cdata_separator.as_ref().map_or("", String::as_str);
+    let text = match e.text {
+        Some(ref text) if ctx.cfg.preserve_cdata => Cow::Borrowed(text.as_str()),
+        None if ctx.cfg.preserve_cdata => Cow::Borrowed(""),
+        _ => element_text(e, separator),
+    };
+    let text = if ctx.cfg.preserve_whitespace_tags.contains(ctx.tag()) {
+        text
+    } else {
+        match ctx.cfg.text_whitespace {
+            TextWhitespace::Trim if text.trim().len() != text.len() => Cow::Owned(text.trim().to_string()),
+            TextWhitespace::Collapse => Cow::Owned(text.split_whitespace().collect::<Vec<_>>().join(" ")),
+            _ => text,
+        }
+    };
+    let text = limit_string(&text, ctx)?;
+    if let Some(ref value_override) = ctx.cfg.value_override {
+        if let Some(value) = value_override(&ctx.path(), &text) {
+            return Ok(value);
+        }
+    }
+    Ok(match type_suffix(ctx.tag(), ctx.cfg) {
+        Some(_) if is_null_token(&text, ctx.cfg) => Value::Null,
+        Some((_, t)) => replace_value(parse_typed(&text, t), ctx.cfg),
+        None => parse_text(&text, ctx.tag(), ctx.cfg),
+    })
+}
+
+/// Adds the text of `e` to `data` under `text_key`; with `preserve_cdata`, adds its CDATA
+/// under `#cdata` and its text only if there is some.
+fn insert_text_contents(data: &mut Map<String, Value>, e: &treexml::Element, ctx: &Context) -> Result<(), ConvertError> {
+    let cfg = ctx.cfg;
+    if !cfg.preserve_cdata || e.text.is_some() {
+        data.insert(cfg.text_key.clone(), parse_text_contents(e, ctx)?);
+    }
+    if let (true, Some(cdata)) = (cfg.preserve_cdata, &e.cdata) {
+        data.insert(CDATA_KEY.to_string(), Value::String(limit_string(cdata, ctx)?.into_owned()));
+    }
+    Ok(())
+}
+
+fn check_required_attributes(e: &treexml::Element, ctx: &Context) -> Result<(), ConvertError> {
+    if let Some(required) = ctx.cfg.required_attributes.get(ctx.tag()) {
+        for attribute in required {
+            if !e.attributes.contains_key(attribute) {
+                return Err(ConvertError::MissingAttribute {
+                    path: ctx.path(),
+                    attribute: attribute.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether `name` is a valid XML name: a letter, `_` or `:`, followed by letters, digits,
+/// `.`, `-`, `_` or `:`.
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' || c == ':' => {
+            chars.all(|c| c.is_alphanumeric() || ['.', '-', '_', ':'].contains(&c))
+        }
+        _ => false,
+    }
+}
+
+/// `strict_wellformed` checks: valid element and attribute names, and no two attributes that
+/// would produce the same key.
+fn check_wellformed(e: &treexml::Element, ctx: &Context) -> Result<(), ConvertError> {
+    let names = e.prefix.iter().chain(Some(&e.name)).chain(e.attributes.keys());
+    if let Some(name) = names.into_iter().find(|name| !is_xml_name(name)) {
+        return Err(ConvertError::InvalidName {
+            path: ctx.path(),
+            name: name.clone(),
+        });
+    }
+    let mut names: Vec<&String> = e.attributes.keys().collect();
+    names.sort();
+    let mut keys = HashMap::new();
+    for name in names {
+        if keys.insert(attribute_key(name, ctx.cfg), name).is_some() {
+            return Err(ConvertError::DuplicateAttribute {
+                path: ctx.path(),
+                attribute: name.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Rejects an aliased key that clashes with a key produced by a different attribute or tag.
+fn check_alias_collision(
+    key: &str,
+    source: &str,
+    previous: Option<&str>,
+    data: &Map<String, Value>,
+    ctx: &Context,
+) -> Result<(), ConvertError> {
+    let aliases = &ctx.cfg.key_aliases;
+    let collides = match previous {
+        Some(previous) => previous != source && (aliases.contains_key(source) || aliases.contains_key(previous)),
+        None => data.contains_key(key) && aliases.contains_key(source),
+    };
+    if collides {
+        Err(ConvertError::KeyCollision {
+            path: ctx.path(),
+            key: key.to_string(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Name of `e` as seen by the tag-based settings, or `None` if `pre_rename` or
+/// `namespace_filter` skips it.
+fn tag_name<'e>(e: &'e treexml::Element, cfg: &Node2ObjectConfig) -> Option<Cow<'e, str>> {
+    if let Some(ref prefixes) = cfg.namespace_filter {
+        if !e.prefix.as_ref().is_some_and(|p| prefixes.contains(p)) {
+            return None;
+        }
+    }
+    match cfg.pre_rename {
+        Some(ref rename) => rename(&e.name).map(Cow::Owned),
+        None => Some(Cow::Borrowed(&e.name)),
+    }
+}
+
+/// Removes repeated scalars from a `dedup_arrays` list, keeping the first of each.
+fn dedup_scalars(items: &mut Vec<Value>, mode: DedupMode) {
+    let is_scalar = |v: &Value| !v.is_array() && !v.is_object();
+    let mut kept: Vec<Value> = Vec::with_capacity(items.len());
+    for item in items.drain(..) {
+        let repeated = is_scalar(&item)
+            && match mode {
+                DedupMode::Consecutive => kept.last() == Some(&item),
+                DedupMode::All => kept.contains(&item),
+            };
+        if !repeated {
+            kept.push(item);
+        }
+    }
+    *items = kept;
+}
+
+/// Turns the converted `samples` of the `timeseries` tag `tag` into one array per field.
+fn transpose_samples(samples: Value, tag: &str, fields: &[String], ctx: &Context) -> Result<Value, ConvertError> {
+    let mut columns: Vec<Vec<Value>> = fields.iter().map(|_| Vec::new()).collect();
+    for sample in samples.as_array().map_or(&[][..], Vec::as_slice) {
+        for (field, column) in fields.iter().zip(columns.iter_mut()) {
+            let value = sample.get(field).map(|v| match v {
+                Value::Array(items) if items.len() == 1 => items[0].clone(),
+                other => other.clone(),
+            });
+            match value {
+                Some(value) => column.push(value),
+                None if ctx.fallible => {
+                    return Err(ConvertError::MissingField {
+                        path: format!("{}/{}/{}", ctx.path(), tag, field),
+                        field: field.clone(),
+                    })
+                }
+                None => column.push(Value::Null),
+            }
+        }
+    }
+    Ok(Value::Object(fields.iter().cloned().zip(columns.into_iter().map(Value::Array)).collect()))
+}
+
+fn convert_node_aux<'e>(
+    e: &'e treexml::Element,
+    name: &str,
+    ctx: &mut Context<'_, 'e>,
+) -> Result<Option<Value>, ConvertError> {
+    ctx.visited += 1;
+    if let Some(ref on_progress) = ctx.cfg.on_progress {
+        if ctx.visited.is_multiple_of(ctx.cfg.progress_interval.max(1)) {
+            on_progress(ctx.visited);
+        }
+    }
+    ctx.path.push(name.to_string());
+    let result = match ctx.cfg.max_depth {
+        Some(max) if ctx.path.len() > max && ctx.fallible => Err(ConvertError::TooDeep { path: ctx.path(), max }),
+        Some(max) if ctx.path.len() > max => {
+            let mut placeholder = Map::new();
+            placeholder.insert(TRUNCATED_KEY.to_string(), Value::Bool(true));
+            Ok(Some(Value::Object(placeholder)))
+        }
+        _ => convert_node_inner(e, ctx),
+    };
+    ctx.path.pop();
+    if let Ok(Some(_)) = result {
+        let pointer = ctx.pointer();
+        if let Some(types) = ctx.node_types.as_mut() {
+            types.insert(pointer, scan_xml_node(e, ctx.cfg.empty_detection));
+        }
+    }
+    result
+}
+
+/// Converts a child named `name` that will be stored at `data[key][index]`.
+fn convert_child<'e>(
+    c: &'e treexml::Element,
+    name: &str,
+    key: &str,
+    index: usize,
+    ctx: &mut Context<'_, 'e>,
+) -> Result<Option<Value>, ConvertError> {
+    if ctx.node_types.is_none() {
+        return convert_node_aux(c, name, ctx);
+    }
+    ctx.pointer.push(key.to_string());
+    ctx.pointer.push(index.to_string());
+    let result = convert_node_aux(c, name, ctx);
+    ctx.pointer.truncate(ctx.pointer.len() - 2);
+    result
+}
+
+/// `(key, value)` pair a child contributes to its parent under `keyed_value`, if its tag is
+/// listed there and it carries the key attribute.
+fn keyed_entry(c: &treexml::Element, name: &str, ctx: &Context) -> Result<Option<(String, Value)>, ConvertError> {
+    let (key_attr, value_attr) = match ctx.cfg.keyed_value.get(name) {
+        Some((key_attr, value_attr)) => (key_attr, value_attr),
+        None => return Ok(None),
+    };
+    let key = match c.attributes.get(key_attr) {
+        Some(key) => key.clone(),
+        None => return Ok(None),
+    };
+    let value = match c.attributes.get(value_attr) {
+        Some(value) => parse_attribute(name, value_attr, &limit_string(value, ctx)?, ctx.cfg),
+        None => Value::Null,
+    };
+    Ok(Some((key, value)))
+}
+
+/// Adds a `keyed_value` entry to `data`, resolving repeated keys by `keyed_duplicate_policy`.
+fn insert_keyed(
+    data: &mut Map<String, Value>,
+    keyed: &mut HashMap<String, usize>,
+    key: String,
+    value: Value,
+    ctx: &Context,
+) -> Result<(), ConvertError> {
+    let seen = keyed.entry(key.clone()).or_insert(0);
+    *seen += 1;
+    if *seen == 1 {
+        data.insert(key, value);
+        return Ok(());
+    }
+    match ctx.cfg.keyed_duplicate_policy {
+        KeyedDuplicatePolicy::Array => {
+            let previous = data.remove(&key).unwrap_or(Value::Null);
+            let mut items = match previous {
+                Value::Array(items) if *seen > 2 => items,
+                previous => vec![previous],
+            };
+            items.push(value);
+            data.insert(key, Value::Array(items));
+        }
+        KeyedDuplicatePolicy::FirstWins => {}
+        KeyedDuplicatePolicy::Error if ctx.fallible => {
+            return Err(ConvertError::DuplicateKey { path: ctx.path(), key });
+        }
+        KeyedDuplicatePolicy::LastWins | KeyedDuplicatePolicy::Error => {
+            data.insert(key, value);
+        }
+    }
+    Ok(())
+}
+
+/// Value of an element whose tag is listed in `value_attribute`: the coerced attribute, if the
+/// element carries it and has no text or children.
+fn attribute_value(e: &treexml::Element, node_type: XMLNodeType, ctx: &Context) -> Result<Option<Value>, ConvertError> {
+    let attribute = match ctx.cfg.value_attribute.get(ctx.tag()) {
+        Some(attribute) if e.attributes.contains_key(attribute) => attribute,
+        _ => return Ok(None),
+    };
+    if node_type != XMLNodeType::Attributes {
+        if ctx.fallible && ctx.cfg.value_attribute_conflict == ValueAttributeConflict::Error {
+            return Err(ConvertError::ValueAttributeConflict {
+                path: ctx.path(),
+                attribute: attribute.clone(),
+            });
+        }
+        return Ok(None);
+    }
+    let value = limit_string(&e.attributes[attribute], ctx)?;
+    Ok(Some(parse_attribute(ctx.tag(), attribute, &value, ctx.cfg)))
+}
+
+/// JSON type named by the text of a `type_from_sibling` type element.
+fn type_from_name(name: &str) -> JsonType {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "int" | "integer" | "long" | "short" => JsonType::Integer,
+        "float" | "double" | "decimal" | "number" => JsonType::Float,
+        "bool" | "boolean" => JsonType::Bool,
+        _ => JsonType::String,
+    }
+}
+
+/// Value of an element holding both children named by `type_from_sibling`, typed as the
+/// type element says.
+fn sibling_typed_value(e: &treexml::Element, ctx: &Context) -> Result<Option<Value>, ConvertError> {
+    let (type_tag, value_tag) = match ctx.cfg.type_from_sibling {
+        Some((ref type_tag, ref value_tag)) => (type_tag, value_tag),
+        None => return Ok(None),
+    };
+    let child = |tag: &str| {
+        e.children
+            .iter()
+            .find(|c| tag_name(c, ctx.cfg).is_some_and(|name| name == tag))
+    };
+    let (type_elem, value_elem) = match (child(type_tag), child(value_tag)) {
+        (Some(t), Some(v)) => (t, v),
+        _ => return Ok(None),
+    };
+    let text = element_text(value_elem, "");
+    let t = type_from_name(type_elem.text.as_ref().map_or("", String::as_str));
+    Ok(Some(replace_value(parse_typed(&limit_string(&text, ctx)?, t), ctx.cfg)))
+}
+
+/// Referenced element of `e` under `resolve_idrefs`: its converted value, `null` if the id is
+/// unknown, or the id itself if the element is already being inlined (a cycle).
+fn resolve_idref<'e>(e: &'e treexml::Element, ctx: &mut Context<'_, 'e>) -> Result<Option<Value>, ConvertError> {
+    let idref = match e.attributes.get(IDREF_ATTRIBUTE) {
+        Some(idref) if ctx.cfg.resolve_idrefs => idref.as_str(),
+        _ => return Ok(None),
+    };
+    let target = match ctx.ids.get(idref) {
+        Some(&target) => target,
+        None => return Ok(Some(Value::Null)),
+    };
+    if !ctx.inlined.insert(idref) {
+        ctx.charge(idref)?;
+        return Ok(Some(Value::String(idref.into())));
+    }
+    ctx.pointer.push(REF_KEY.to_string());
+    let value = convert_node_aux(target, &root_name(target, ctx.cfg), ctx);
+    ctx.pointer.pop();
+    Ok(Some(value?.unwrap_or(Value::Null)))
+}
+
+fn convert_node_inner<'e>(e: &'e treexml::Element, ctx: &mut Context<'_, 'e>) -> Result<Option<Value>, ConvertError> {
+    if ctx.fallible {
+        if ctx.cfg.strict_wellformed {
+            check_wellformed(e, ctx)?;
+        }
+        check_required_attributes(e, ctx)?;
+    }
+    let cfg = ctx.cfg;
+    let node_type = match scan_xml_node(e, cfg.empty_detection) {
+        XMLNodeType::Empty if !missing_defaults(e, ctx.tag(), cfg).is_empty() => XMLNodeType::Attributes,
+        XMLNodeType::Text if !missing_defaults(e, ctx.tag(), cfg).is_empty() => XMLNodeType::TextAndAttributes,
+        node_type => node_type,
+    };
+    if node_type == XMLNodeType::Parent {
+        if let Some(v) = sibling_typed_value(e, ctx)? {
+            return Ok(Some(v));
+        }
+    }
+    if let Some(v) = attribute_value(e, node_type, ctx)? {
+        return Ok(Some(v));
+    }
+    let mut value = match node_type {
+        XMLNodeType::Parent | XMLNodeType::SemiStructured => {
+            let mut data = convert_attributes(e, ctx)?;
+            // Source tag name of every key produced by a child so far.
+            let mut vectorized = HashMap::new();
+            // Keys of prefixed children to move under their prefix for `NamespaceMode::Expand`.
+            let mut expanded = Vec::new();
+            // Number of `keyed_value` entries seen for every key.
+            let mut keyed = HashMap::new();
+
+            for c in &e.children {
+                let name = match tag_name(c, cfg) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                if let Some((entry_key, entry)) = keyed_entry(c, &name, ctx)? {
+                    ctx.charge(&entry_key)?;
+                    if !vectorized.contains_key(&entry_key) {
+                        insert_keyed(&mut data, &mut keyed, entry_key, entry, ctx)?;
+                    } else if ctx.fallible && cfg.attribute_child_collision == AttributeChildCollision::Error {
+                        return Err(ConvertError::AttributeCollision { path: ctx.path(), key: entry_key });
+                    } else if let Some(Value::Array(items)) = data.get_mut(&entry_key) {
+                        items.push(entry);
+                    }
+                    continue;
+                }
+                let key = element_key(c, &name, cfg);
+                let index = match vectorized.get(&key) {
+                    Some(_) => data.get(&key).and_then(Value::as_array).map_or(0, Vec::len),
+                    None => 0,
+                };
+                if let Some(v) = convert_child(c, &name, &key, index, ctx)? {
+                    if ctx.fallible {
+                        let previous = vectorized.get(&key).map(|p: &Cow<str>| p.as_ref());
+                        check_alias_collision(&key, &name, previous, &data, ctx)?;
+                    }
+                    match vectorized.entry(key) {
+                        Entry::Vacant(slot) => {
+                            if let (NamespaceMode::Expand, Some(prefix)) = (cfg.namespace_mode, &c.prefix) {
+                                expanded.push((slot.key().clone(), prefix));
+                            }
+                            ctx.charge(slot.key())?;
+                            let mut items = vec![v];
+                            if let Some(attribute) = data.remove(slot.key()) {
+                                if ctx.fallible && cfg.attribute_child_collision == AttributeChildCollision::Error {
+                                    return Err(ConvertError::AttributeCollision {
+                                        path: ctx.path(),
+                                        key: slot.key().clone(),
+                                    });
+                                }
+                                items.insert(0, attribute);
+                            }
+                            data.insert(slot.key().clone(), Value::Array(items));
+                            slot.insert(name);
+                        }
+                        Entry::Occupied(slot) => match data.get_mut(slot.key()) {
+                            Some(Value::Array(items)) => items.push(v),
+                            Some(other) => {
+                                let first = other.take();
+                                *other = Value::Array(vec![first, v]);
+                            }
+                            None => {
+                                data.insert(slot.key().clone(), Value::Array(vec![v]));
+                            }
+                        },
+                    }
+                }
+            }
+            for (key, source) in &vectorized {
+                if cfg.dedup_arrays.contains(source.as_ref()) {
+                    if let Some(Value::Array(items)) = data.get_mut(key) {
+                        dedup_scalars(items, cfg.dedup_mode);
+                    }
+                }
+                if let Some(fields) = cfg.timeseries.get(source.as_ref()) {
+                    let samples = data.remove(key).unwrap_or(Value::Null);
+                    data.insert(key.clone(), transpose_samples(samples, source, fields, ctx)?);
+                }
+                if cfg.annotate_counts {
+                    if let Some(len) = data[key].as_array().map(Vec::len) {
+                        data.insert(format!("{}{}", key, COUNT_SUFFIX), Value::Number(len.into()));
+                    }
+                }
+            }
+            let unwrap = cfg.auto_unwrap_scalar_lists && node_type == XMLNodeType::Parent;
+            if unwrap && vectorized.len() == 1 && data.len() == 1 {
+                let items = data.values().next().and_then(Value::as_array);
+                if items.is_some_and(|items| items.iter().all(|v| !v.is_array() && !v.is_object())) {
+                    return Ok(data.into_iter().next().map(|(_, items)| items));
+                }
+            }
+            if cfg.collapse_single_element_arrays {
+                for key in vectorized.keys().filter(|key| !cfg.force_list.contains(key.as_str())) {
+                    if let Some(Value::Array(items)) = data.get_mut(key) {
+                        if items.len() == 1 {
+                            let item = items.pop().unwrap_or(Value::Null);
+                            data.insert(key.clone(), item);
+                        }
+                    }
+                }
+            }
+            for (key, prefix) in expanded {
+                let count_key = format!("{}{}", key, COUNT_SUFFIX);
+                if let Some(count) = data.remove(&count_key) {
+                    let group = data.entry(prefix.clone()).or_insert_with(|| Value::Object(Map::new()));
+                    if let Value::Object(ref mut group) = *group {
+                        group.insert(count_key[prefix.len() + 1..].to_string(), count);
+                    }
+                }
+                let value = data.remove(&key).unwrap_or(Value::Null);
+                let group = data.entry(prefix.clone()).or_insert_with(|| Value::Object(Map::new()));
+                match *group {
+                    Value::Object(ref mut group) => {
+                        group.insert(key[prefix.len() + 1..].to_string(), value);
+                    }
+                    _ => {
+                        data.insert(key, value);
+                    }
+                }
+            }
+            if node_type == XMLNodeType::SemiStructured {
+                insert_text_contents(&mut data, e, ctx)?;
+            }
+            Some(Value::Object(data))
+        }
+        XMLNodeType::Text if cfg.preserve_cdata && e.cdata.is_some() => {
+            let mut data = Map::new();
+            insert_text_contents(&mut data, e, ctx)?;
+            Some(Value::Object(data))
+        }
+        XMLNodeType::Text => Some(parse_text_contents(e, ctx)?),
+        XMLNodeType::Attributes => Some(Value::Object(convert_attributes(e, ctx)?)),
+        XMLNodeType::TextAndAttributes => {
+            let mut data = convert_attributes(e, ctx)?;
+            insert_text_contents(&mut data, e, ctx)?;
+            Some(Value::Object(data))
+        }
+        XMLNodeType::Empty => empty_value(cfg),
+    };
+    if let Some(Value::Object(ref mut data)) = value {
+        if let Some(target) = resolve_idref(e, ctx)? {
+            data.insert(REF_KEY.to_string(), target);
+        }
+    }
+    Ok(value)
+}
+
+fn convert_root<'e>(e: &'e treexml::Element, ctx: &mut Context<'_, 'e>) -> Result<Map<String, Value>, ConvertError> {
+    ctx.index_ids(e);
+    if let Some(ref unwrap_path) = ctx.cfg.unwrap_path {
+        if let Some(inner) = records::find_elements(e, unwrap_path, ctx.cfg).into_iter().next() {
+            return match convert_element(inner, ctx)? {
+                Some(Value::Object(data)) => Ok(data),
+                value => {
+                    let mut data = Map::new();
+                    if let Some(value) = value {
+                        data.insert(root_key(inner, ctx.cfg), value);
+                    }
+                    Ok(data)
+                }
+            };
+        }
+    }
+    convert_element(e, ctx).map(|value| {
+        let mut data = Map::new();
+        if let Some(value) = value {
+            data.insert(root_key(e, ctx.cfg), value);
+        }
+        data
+    })
+}
+
+/// Name of a top-level element; `pre_rename` and `namespace_filter` cannot skip it.
+fn root_name<'e>(e: &'e treexml::Element, cfg: &Node2ObjectConfig) -> Cow<'e, str> {
+    tag_name(e, cfg).unwrap_or(Cow::Borrowed(&e.name))
+}
+
+/// Output key of a top-level element: `root_key` if set, otherwise derived from its name.
+fn root_key(e: &treexml::Element, cfg: &Node2ObjectConfig) -> String {
+    match cfg.root_key {
+        Some(ref key) => key.clone(),
+        None => prefixed_key(e, convert_key(&root_name(e, cfg), cfg), cfg),
+    }
+}
+
+/// Converts `e` as the top-level element of the output, or `None` if `EmptyRepr::Omit` leaves
+/// it out.
+fn convert_element<'e>(e: &'e treexml::Element, ctx: &mut Context<'_, 'e>) -> Result<Option<Value>, ConvertError> {
+    let name = root_name(e, ctx.cfg);
+    let key = root_key(e, ctx.cfg);
+    ctx.pointer.push(key.clone());
+    let value = convert_node_aux(e, &name, ctx);
+    ctx.pointer.pop();
+    let mut value = match value? {
+        Some(value) => value,
+        None if ctx.cfg.empty_element == EmptyRepr::Omit => return Ok(None),
+        None => Value::Null,
+    };
+    if let Some(output) = ctx.cfg.numeric_output {
+        normalize_numbers(&mut value, output, &mut vec![key], ctx)?;
+    }
+    Ok(Some(value))
+}
+
+/// Rewrites every number under `v` as `numeric_output` asks. `keys` is the path of keys and
+/// indexes to `v`, for error messages.
+fn normalize_numbers(
+    v: &mut Value,
+    output: NumericOutput,
+    keys: &mut Vec<String>,
+    ctx: &Context,
+) -> Result<(), ConvertError> {
+    match *v {
+        Value::Number(ref n) => {
+            let normalized = match output {
+                NumericOutput::AllFloat => n.as_f64().and_then(Number::from_f64).map(Value::Number),
+                NumericOutput::AllString => Some(Value::String(n.to_string())),
+                NumericOutput::AllInt if n.is_i64() || n.is_u64() => None,
+                NumericOutput::AllInt => {
+                    let f = n.as_f64().unwrap_or_default();
+                    let reject = ctx.fallible && ctx.cfg.fractional_int_policy == FractionalIntPolicy::Error;
+                    if reject && f.fract() != 0.0 {
+                        return Err(ConvertError::FractionalNumber {
+                            path: keys.join("/"),
+                            value: n.to_string(),
+                        });
+                    }
+                    let rounded = f.round();
+                    if rounded.abs() < 9.2e18 {
+                        Some(Value::Number((rounded as i64).into()))
+                    } else {
+                        None
+                    }
+                }
+            };
+            if let Some(normalized) = normalized {
+                *v = normalized;
+            }
+        }
+        Value::Array(ref mut items) => for (i, item) in items.iter_mut().enumerate() {
+            keys.push(i.to_string());
+            normalize_numbers(item, output, keys, ctx)?;
+            keys.pop();
+        },
+        Value::Object(ref mut obj) => for (k, item) in obj.iter_mut() {
+            keys.push(k.clone());
+            normalize_numbers(item, output, keys, ctx)?;
+            keys.pop();
+        },
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Converts treexml::Element into a serde_json hashmap, reporting documents rejected by the
+/// validating settings (such as `required_attributes`) as errors.
+pub fn try_node2object(e: &treexml::Element, cfg: &Node2ObjectConfig) -> Result<Map<String, Value>, ConvertError> {
+    convert_root(e, &mut Context::new(cfg, true))
+}
+
+/// Converts a whole treexml::Document like `try_node2object`, wrapped in Value::Object.
+/// Documents without a root element give what `empty_document` asks for.
+pub fn document2object(doc: &treexml::Document, cfg: &Node2ObjectConfig) -> Result<Value, ConvertError> {
+    match (&doc.root, cfg.empty_document) {
+        (Some(root), _) => try_node2object(root, cfg).map(Value::Object),
+        (None, EmptyDocument::EmptyMap) => Ok(Value::Object(Map::new())),
+        (None, EmptyDocument::Null) => Ok(Value::Null),
+        (None, EmptyDocument::Error) => Err(ConvertError::EmptyDocument { path: String::new() }),
+    }
+}
+
+/// Converts treexml::Element into a serde_json hashmap using the given settings.
+/// Validating settings are ignored; use `try_node2object` to enforce them.
+pub fn node2object_with(e: &treexml::Element, cfg: &Node2ObjectConfig) -> Map<String, Value> {
+    convert_root(e, &mut Context::new(cfg, false)).unwrap_or_default()
+}
+
+/// Converts only the first element at `path`, a list of output keys starting with the root
+/// (e.g. `["population", "entry"]`), as if it were the root, using the given settings.
+/// Returns `None` if no element matches.
+pub fn node2object_at_with(root: &treexml::Element, path: &[&str], cfg: &Node2ObjectConfig) -> Option<Map<String, Value>> {
+    let target = *records::find_elements(root, &path.join("/"), cfg).first()?;
+    let mut ctx = Context::new(cfg, false);
+    ctx.index_ids(root);
+    let mut data = Map::new();
+    if let Some(value) = convert_element(target, &mut ctx).unwrap_or_default() {
+        data.insert(root_key(target, cfg), value);
+    }
+    Some(data)
+}
+
+/// Converts only the first element at `path` using the default settings; see
+/// `node2object_at_with`.
+pub fn node2object_at(root: &treexml::Element, path: &[&str]) -> Option<Map<String, Value>> {
+    node2object_at_with(root, path, &Node2ObjectConfig::default())
+}
+
+/// Converts treexml::Element like `node2object`, also reporting the node type each element
+/// was classified as, keyed by the JSON pointer of its converted value (e.g.
+/// `/population/entry/0/name/0`).
+pub fn convert_with_node_types(e: &treexml::Element) -> (Value, HashMap<String, XMLNodeType>) {
+    let cfg = Node2ObjectConfig::default();
+    let mut ctx = Context::new(&cfg, false);
+    ctx.node_types = Some(HashMap::new());
+    let data = convert_root(e, &mut ctx).unwrap_or_default();
+    (Value::Object(data), ctx.node_types.unwrap_or_default())
+}
+
+/// Converts treexml::Element into a serde_json hashmap. The latter can be wrapped in Value::Object.
+pub fn node2object(e: &treexml::Element) -> Map<String, Value> {
+    node2object_with(e, &Node2ObjectConfig::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::rc::Rc;
+    use test::Bencher;
+
+    #[bench]
+    fn bench(b: &mut Bencher) {
+        let xml = include_str!("../examples/xml.xml");
+        b.iter(|| {
+            let n = test::black_box(10);
+            (0..n).for_each(|_| {
+                let xml = treexml::Document::parse(xml.as_bytes()).unwrap();
+                let xml = xml.root.unwrap();
+                let _ = node2object(&xml);
+            })
+        })
+    }
+
+    #[bench]
+    fn bench_small_documents(b: &mut Bencher) {
+        let documents: Vec<treexml::Element> = (0..100)
+            .map(|i| {
+                let xml = format!(r#"<Entry entryId="{}"><FullName>Alex</FullName><HeightCm>173.5</HeightCm></Entry>"#, i);
+                treexml::Document::parse(xml.as_bytes()).unwrap().root.unwrap()
+            })
+            .collect();
+        b.iter(|| {
+            for e in &documents {
+                test::black_box(node2object(e));
+            }
+        })
+    }
+
+    #[test]
+    fn smart_list_detection() {
+        let raw_xml = treexml::Document::parse(r#"<a>
+            <b first="1"/>
+            <b first="2"/>
+            <c first="3"/>
+            </a>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let actual = Value::Object(node2object(&raw_xml));
+        assert_eq!(actual, json!({
+            "a": {
+                "b": [ { "first": "1" }, { "first": "2" } ],
+                "c": [ { "first": "3" } ]
+            }
+        }));
+    }
+
+    #[test]
+    fn html_compat_preset() {
+        let dom_root = treexml::Document::parse(r#"<form id="login">
+            <input type="checkbox" name="remember" checked="checked"/>
+            <input type="text" maxLength="10" disabled=""/>
+            <helpText></helpText>
+            </form>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig::html_compat();
+        cfg.coerce_numbers = true;
+        let actual = Value::Object(node2object_with(&dom_root, &cfg));
+        assert_eq!(actual, json!({
+            "form": {
+                "id": "login",
+                "input": [
+                    { "type": "checkbox", "name": "remember", "checked": true },
+                    { "type": "text", "maxLength": "10", "disabled": true }
+                ],
+                "helpText": [""]
+            }
+        }));
+    }
+
+    #[test]
+    fn required_attributes() {
+        let dom_root = treexml::Document::parse(r#"<html>
+            <head>
+              <link rel="stylesheet" href="main.css"/>
+              <link rel="icon"/>
+            </head>
+            </html>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig::default();
+        cfg.required_attributes.insert("link".into(), vec!["href".into()]);
+
+        assert_eq!(try_node2object(&dom_root, &cfg), Err(ConvertError::MissingAttribute {
+            path: "html/head/link".into(),
+            attribute: "href".into(),
+        }));
+        assert_eq!(node2object_with(&dom_root, &cfg), node2object(&dom_root));
+    }
+
+    #[test]
+    fn extract_population_records() {
+        let dom_root = treexml::Document::parse(r#"<population>
+            <entry><name>Alex</name></entry>
+            <entry><name>Mel</name></entry>
+            <visitor><name>Kolya</name></visitor>
+            </population>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig::default();
+
+        assert_eq!(
+            extract_records(&dom_root, "population/entry", &cfg),
+            vec![json!({ "name": ["Alex"] }), json!({ "name": ["Mel"] })]
+        );
+        assert!(extract_records(&dom_root, "other/entry", &cfg).is_empty());
+    }
+
+    #[test]
+    fn key_aliases() {
+        let dom_root = treexml::Document::parse(r#"<order>
+            <sku>A-1</sku>
+            <qty>3</qty>
+            </order>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig::default();
+        cfg.key_aliases.insert("qty".into(), "quantity".into());
+
+        assert_eq!(Value::Object(try_node2object(&dom_root, &cfg).unwrap()), json!({
+            "order": { "sku": ["A-1"], "quantity": ["3"] }
+        }));
+
+        cfg.key_aliases.insert("sku".into(), "quantity".into());
+        assert_eq!(try_node2object(&dom_root, &cfg), Err(ConvertError::KeyCollision {
+            path: "order".into(),
+            key: "quantity".into(),
+        }));
+    }
+
+    #[test]
+    fn text_key_round_trip() {
+        let mut fixture = treexml::Element::new("player");
+        fixture.text = Some("Kolya".into());
+        fixture.attributes.insert("score".into(), "9000".into());
+        let cfg = Node2ObjectConfig {
+            text_key: "$text".into(),
+            ..Node2ObjectConfig::default()
+        };
+
+        let converted = node2object_with(&fixture, &cfg);
+        assert_eq!(Value::Object(converted.clone()), json!({ "player": { "$text": "Kolya", "score": "9000" } }));
+        let inner = converted["player"].as_object().unwrap();
+        assert_eq!(object2node_with("player", inner, &cfg), fixture);
+    }
+
+    #[test]
+    fn thousands_separator() {
+        let dom_root = treexml::Document::parse(r#"<invoice>
+            <amount>1,234.56</amount>
+            <ratio>1,5</ratio>
+            <code>1,234</code>
+            </invoice>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            thousands_separator: Some(','),
+            coerce_only: Some(HashSet::new()),
+            ..Node2ObjectConfig::default()
+        };
+        cfg.coerce_only.as_mut().unwrap().insert("amount".into());
+        cfg.coerce_only.as_mut().unwrap().insert("ratio".into());
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "invoice": { "amount": [1234.56], "ratio": ["1,5"], "code": ["1,234"] }
+        }));
+
+        let european = Node2ObjectConfig {
+            thousands_separator: Some('.'),
+            decimal_separator: ',',
+            ..cfg
+        };
+        let mut fixture = treexml::Element::new("amount");
+        fixture.text = Some("1.234,56".into());
+        assert_eq!(Value::Object(node2object_with(&fixture, &european)), json!({ "amount": 1234.56 }));
+    }
+
+    #[test]
+    fn node_types_by_pointer() {
+        let dom_root = treexml::Document::parse("
+            <population>
+              <entry>
+                <name>Alex</name>
+                <height>173.5</height>
+              </entry>
+              <entry>
+                <name>Mel</name>
+                <height>180.4</height>
+              </entry>
+            </population>
+        ".as_bytes()).unwrap().root.unwrap();
+        let (value, types) = convert_with_node_types(&dom_root);
+
+        assert_eq!(value, Value::Object(node2object(&dom_root)));
+        assert_eq!(types.len(), 7);
+        assert_eq!(types["/population"], XMLNodeType::Parent);
+        for entry in &["/population/entry/0", "/population/entry/1"] {
+            assert_eq!(types[*entry], XMLNodeType::Parent);
+            assert_eq!(types[&format!("{}/name/0", entry)], XMLNodeType::Text);
+            assert!(value.pointer(&format!("{}/name/0", entry)).unwrap().is_string());
+        }
+    }
+
+    #[test]
+    fn max_string_len() {
+        let mut fixture = treexml::Element::new("body");
+        fixture.text = Some("abcdefghij".into());
+        let mut cfg = Node2ObjectConfig {
+            max_string_len: Some(4),
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(try_node2object(&fixture, &cfg).unwrap()), json!({ "body": "abcd…" }));
+
+        cfg.max_string_policy = StringLimitPolicy::Error;
+        assert_eq!(try_node2object(&fixture, &cfg), Err(ConvertError::StringTooLong {
+            path: "body".into(),
+            len: 10,
+            max: 4,
+        }));
+
+        fixture.text = None;
+        fixture.attributes.insert("title".into(), "abcdefghij".into());
+        assert!(try_node2object(&fixture, &cfg).is_err());
+        assert_eq!(Value::Object(node2object_with(&fixture, &cfg)), json!({ "body": { "title": "abcd…" } }));
+    }
+
+    #[test]
+    fn dual_number() {
+        let mut fixture = treexml::Element::new("price");
+        fixture.text = Some("1.50".into());
+        let cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            dual_number: true,
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&fixture, &cfg)), json!({
+            "price": { "$num": 1.5, "$raw": "1.50" }
+        }));
+    }
+
+    #[test]
+    fn soap_preset() {
+        let dom_root = treexml::Document::parse(r#"
+            <soapenv:Envelope xmlns:soapenv="http://schemas.xmlsoap.org/soap/envelope/"
+                              soapenv:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/">
+              <soapenv:Header/>
+              <soapenv:Body>
+                <m:GetOrderResponse xmlns:m="https://www.example.org/orders">
+                  <m:OrderId>00042</m:OrderId>
+                  <m:Total>34.5</m:Total>
+                </m:GetOrderResponse>
+              </soapenv:Body>
+            </soapenv:Envelope>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig::soap();
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "GetOrderResponse": [ { "OrderId": ["00042"], "Total": ["34.5"] } ]
+        }));
+
+        cfg.unwrap_path = None;
+        assert_eq!(
+            node2object_with(&dom_root, &cfg)["Envelope"]["encodingStyle"],
+            json!("http://schemas.xmlsoap.org/soap/encoding/")
+        );
+    }
+
+    #[test]
+    fn pre_rename() {
+        let dom_root = treexml::Document::parse(r#"<ledger>
+            <legacy_amount>12.5</legacy_amount>
+            <amount>3</amount>
+            <comment>3</comment>
+            <internal>secret</internal>
+            </ledger>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            coerce_only: Some(["amount"].iter().map(|t| t.to_string()).collect()),
+            pre_rename: Some(Rc::new(|name: &str| match name {
+                "legacy_amount" => Some("amount".to_string()),
+                "internal" => None,
+                _ => Some(name.to_string()),
+            })),
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "ledger": { "amount": [12.5, 3], "comment": ["3"] }
+        }));
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg.clone())), json!({
+            "ledger": { "amount": [12.5, 3], "comment": ["3"] }
+        }));
+        assert!(format!("{:?}", cfg).contains("pre_rename: Some(..)"));
+
+        let dom_root = treexml::Document::parse(r#"<feed>
+            <legacy_entry>a</legacy_entry>
+            <other>b</other>
+            </feed>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig {
+            collapse_single_element_arrays: true,
+            force_list: ["entry"].iter().map(|t| t.to_string()).collect(),
+            ..Node2ObjectConfig::default()
+        };
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "feed": { "legacy_entry": "a", "other": "b" }
+        }));
+        cfg.pre_rename = Some(Rc::new(|name: &str| match name {
+            "legacy_entry" => Some("entry".to_string()),
+            _ => Some(name.to_string()),
+        }));
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "feed": { "entry": ["a"], "other": "b" }
+        }));
+    }
+
+    #[test]
+    fn type_suffixes() {
+        let dom_root = treexml::Document::parse(r#"<reading>
+            <value_s>007</value_s>
+            <count_i>42</count_i>
+            <ok_b>true</ok_b>
+            <label>7</label>
+            </reading>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig {
+            strip_type_suffix: true,
+            ..Node2ObjectConfig::default()
+        };
+        cfg.type_suffix_map.insert("_s".into(), JsonType::String);
+        cfg.type_suffix_map.insert("_i".into(), JsonType::Integer);
+        cfg.type_suffix_map.insert("_b".into(), JsonType::Bool);
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "reading": { "value": ["007"], "count": [42], "ok": [true], "label": ["7"] }
+        }));
+    }
+
+    #[test]
+    fn text_cdata_separator() {
+        let mut fixture = treexml::Element::new("note");
+        fixture.text = Some("hello".into());
+        fixture.cdata = Some("world".into());
+        let mut cfg = Node2ObjectConfig::default();
+
+        assert_eq!(Value::Object(node2object_with(&fixture, &cfg)), json!({ "note": "helloworld" }));
+        cfg.text_cdata_separator = Some(" ".into());
+        assert_eq!(Value::Object(node2object_with(&fixture, &cfg)), json!({ "note": "hello world" }));
+        fixture.text = None;
+        assert_eq!(Value::Object(node2object_with(&fixture, &cfg)), json!({ "note": "world" }));
+    }
+
+    #[test]
+    fn preserve_date_like() {
+        let dom_root = treexml::Document::parse(r#"<event>
+            <d>2020-01-01</d>
+            <y>2020</y>
+            <m>--05</m>
+            <at>2020-01-01T12:30:00.5+03:00</at>
+            <n>20201</n>
+            </event>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            ..Node2ObjectConfig::default()
+        };
+        assert_eq!(node2object_with(&dom_root, &cfg)["event"]["y"], json!([2020]));
+
+        cfg.preserve_date_like = true;
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "event": {
+                "d": ["2020-01-01"],
+                "y": ["2020"],
+                "m": ["--05"],
+                "at": ["2020-01-01T12:30:00.5+03:00"],
+                "n": [20201]
+            }
+        }));
+
+        let dom_root = treexml::Document::parse("<r><x>résumé</x><x>é12345</x></r>".as_bytes()).unwrap().root.unwrap();
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({ "r": { "x": ["résumé", "é12345"] } }));
+    }
+
+    #[test]
+    fn timeseries() {
+        let dom_root = treexml::Document::parse(r#"<series unit="ms">
+            <sample><t>1</t><v>10</v><note>warmup</note></sample>
+            <sample><t>2</t><v>20</v></sample>
+            <sample><t>3</t><v>30</v></sample>
+            </series>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            ..Node2ObjectConfig::default()
+        };
+        cfg.timeseries.insert("sample".into(), vec!["t".into(), "v".into()]);
+
+        assert_eq!(Value::Object(try_node2object(&dom_root, &cfg).unwrap()), json!({
+            "series": {
+                "unit": "ms",
+                "sample": { "t": [1, 2, 3], "v": [10, 20, 30] }
+            }
+        }));
+
+        cfg.timeseries.insert("sample".into(), vec!["t".into(), "note".into()]);
+        assert_eq!(try_node2object(&dom_root, &cfg), Err(ConvertError::MissingField {
+            path: "series/sample/note".into(),
+            field: "note".into(),
+        }));
+    }
+
+    #[test]
+    fn null_tokens() {
+        let dom_root = treexml::Document::parse(r#"<row>
+            <value>null</value>
+            <other>NULL</other>
+            <name>nullable</name>
+            </row>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig::default();
+        cfg.null_tokens.insert("null".into());
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "row": { "value": [null], "other": ["NULL"], "name": ["nullable"] }
+        }));
+
+        cfg.null_tokens_ignore_case = true;
+        assert_eq!(node2object_with(&dom_root, &cfg)["row"]["other"], json!([null]));
+    }
+
+    #[test]
+    fn empty_detection() {
+        let mut blank = treexml::Element::new("a");
+        blank.text = Some("  ".into());
+        let parsed: Vec<treexml::Element> = ["<a/>", "<a></a>", "<a>  </a>"]
+            .iter()
+            .map(|xml| treexml::Document::parse(xml.as_bytes()).unwrap().root.unwrap())
+            .collect();
+        let mut cfg = Node2ObjectConfig::default();
+
+        for e in &parsed {
+            assert_eq!(scan_xml_node(e, EmptyDetection::Strict), XMLNodeType::Empty);
+            assert_eq!(scan_xml_node(e, EmptyDetection::IgnoreWhitespace), XMLNodeType::Empty);
+        }
+        assert_eq!(scan_xml_node(&blank, EmptyDetection::Strict), XMLNodeType::Text);
+        assert_eq!(Value::Object(node2object_with(&blank, &cfg)), json!({ "a": "  " }));
+
+        cfg.empty_detection = EmptyDetection::IgnoreWhitespace;
+        assert_eq!(scan_xml_node(&blank, cfg.empty_detection), XMLNodeType::Empty);
+        assert_eq!(Value::Object(node2object_with(&blank, &cfg)), json!({ "a": null }));
+    }
+
+    #[test]
+    fn whitespace_in_mixed_content() {
+        let parsed = treexml::Document::parse("<p>\n  <b>x</b>\n</p>".as_bytes()).unwrap().root.unwrap();
+        let mut built = treexml::Element::new("p");
+        built.text = Some("\n  \n".into());
+        built.children.push(treexml::Element {
+            text: Some("x".into()),
+            ..treexml::Element::new("b")
+        });
+        let cfg = Node2ObjectConfig {
+            empty_detection: EmptyDetection::IgnoreWhitespace,
+            ..Node2ObjectConfig::default()
+        };
+
+        // The parser drops whitespace-only segments between children by itself.
+        assert_eq!(parsed.text, None);
+        assert_eq!(Value::Object(node2object(&parsed)), json!({ "p": { "b": ["x"] } }));
+        assert_eq!(scan_xml_node(&built, EmptyDetection::Strict), XMLNodeType::SemiStructured);
+        assert_eq!(Value::Object(node2object_with(&built, &cfg)), json!({ "p": { "b": ["x"] } }));
+    }
+
+    #[test]
+    fn value_replacements() {
+        let dom_root = treexml::Document::parse(r#"<row code="N/A">
+            <price>N/A</price>
+            <discount>-</discount>
+            <note>N/A or -</note>
+            </row>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig::default();
+        cfg.value_replacements.insert("N/A".into(), Value::Null);
+        cfg.value_replacements.insert("-".into(), json!(""));
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "row": { "code": null, "price": [null], "discount": [""], "note": ["N/A or -"] }
+        }));
+    }
+
+    #[test]
+    fn on_progress() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let xml = include_str!("../examples/xml.xml");
+        let dom_root = treexml::Document::parse(xml.as_bytes()).unwrap().root.unwrap();
+        // The root, 40 entries and 8 elements per entry below them.
+        let total = 1 + 40 * 9;
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let seen = calls.clone();
+        let cfg = Node2ObjectConfig {
+            on_progress: Some(Rc::new(move |done| seen.borrow_mut().push(done))),
+            progress_interval: 100,
+            ..Node2ObjectConfig::default()
+        };
+        node2object_with(&dom_root, &cfg);
+
+        let expected: Vec<usize> = (1..=total / 100).map(|i| i * 100).collect();
+        assert_eq!(expected.len(), 3);
+        assert_eq!(*calls.borrow(), expected);
+    }
+
+    #[test]
+    fn attributes_as_list() {
+        let mut fixture = treexml::Element::new("item");
+        fixture.attributes.insert("a:id".into(), "1".into());
+        fixture.attributes.insert("b:id".into(), "2".into());
+        let mut cfg = Node2ObjectConfig::default();
+        cfg.strip_prefixes.insert("a".into());
+        cfg.strip_prefixes.insert("b".into());
+
+        assert_eq!(node2object_with(&fixture, &cfg)["item"].as_object().unwrap().len(), 1);
+        cfg.attributes_as_list = true;
+        assert_eq!(Value::Object(node2object_with(&fixture, &cfg)), json!({
+            "item": { "@attributes": [ { "name": "id", "value": "1" }, { "name": "id", "value": "2" } ] }
+        }));
+    }
+
+    #[test]
+    fn type_from_sibling() {
+        let dom_root = treexml::Document::parse(r#"<record>
+            <field><type>int</type><value>42</value></field>
+            <field><type>string</type><value>007</value></field>
+            <other><value>1</value></other>
+            </record>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            type_from_sibling: Some(("type".into(), "value".into())),
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "record": { "field": [42, "007"], "other": [ { "value": ["1"] } ] }
+        }));
+    }
+
+    #[test]
+    fn raw_fallback() {
+        let dom_root = treexml::Document::parse(r#"<row>
+            <year>2020</year>
+            <code>007</code>
+            <count>42</count>
+            </row>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            preserve_date_like: true,
+            raw_fallback: true,
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "row": {
+                "year": [ { "value": "2020", "#raw": "2020" } ],
+                "code": [ { "value": 7, "#raw": "007" } ],
+                "count": [42]
+            }
+        }));
+    }
+
+    #[test]
+    fn resolve_idrefs() {
+        let dom_root = treexml::Document::parse(r#"<graph>
+            <node id="a" idref="b"><label>start</label></node>
+            <node id="b" idref="a"/>
+            <node id="c" idref="missing"/>
+            </graph>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            resolve_idrefs: true,
+            ..Node2ObjectConfig::default()
+        };
+
+        let b_from_a = json!({ "id": "b", "idref": "a", "#ref": {
+            "id": "a", "idref": "b", "label": ["start"], "#ref": "b"
+        } });
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "graph": {
+                "node": [
+                    { "id": "a", "idref": "b", "label": ["start"], "#ref": b_from_a },
+                    { "id": "b", "idref": "a", "#ref": "a" },
+                    { "id": "c", "idref": "missing", "#ref": null }
+                ]
+            }
+        }));
+    }
+
+    #[test]
+    fn shared_idrefs() {
+        let dom_root = treexml::Document::parse(r#"
+            <r><n id="a"><v>1</v></n><p idref="a"/><q idref="a"/></r>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            resolve_idrefs: true,
+            ..Node2ObjectConfig::default()
+        };
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "r": {
+                "n": [{ "id": "a", "v": ["1"] }],
+                "p": [{ "idref": "a", "#ref": { "id": "a", "v": ["1"] } }],
+                "q": [{ "idref": "a", "#ref": "a" }]
+            }
+        }));
+
+        // Every level refers to the next one twice; inlining each reference would double
+        // the output with every level.
+        let levels: String = (0..32)
+            .map(|i| format!(r#"<l id="l{0}"><c idref="l{1}"/><c idref="l{1}"/></l>"#, i, i + 1))
+            .collect();
+        let xml = format!("<r>{}<l id=\"l32\"/></r>", levels);
+        let dom_root = treexml::Document::parse(xml.as_bytes()).unwrap().root.unwrap();
+        assert!(Value::Object(node2object_with(&dom_root, &cfg)).to_string().len() < 10 * xml.len());
+        let budget = Node2ObjectConfig {
+            max_total_string_bytes: Some(2 * xml.len()),
+            ..cfg
+        };
+        assert!(try_node2object(&dom_root, &budget).is_ok());
+    }
+
+    #[test]
+    fn namespace_filter() {
+        let dom_root = treexml::Document::parse(r#"<metadata
+            xmlns:dc="http://purl.org/dc/elements/1.1/"
+            xmlns:foaf="http://xmlns.com/foaf/0.1/">
+            <dc:title>Moby Dick</dc:title>
+            <foaf:name>Herman Melville</foaf:name>
+            <dc:date>1851</dc:date>
+            <note>first edition</note>
+            </metadata>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            namespace_filter: Some(["dc".to_string()].iter().cloned().collect()),
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "metadata": { "title": ["Moby Dick"], "date": ["1851"] }
+        }));
+    }
+
+    #[test]
+    fn root_key() {
+        let dom_root = treexml::Document::parse("<anything><id>1</id></anything>".as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            root_key: Some("document".to_string()),
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "document": { "id": ["1"] }
+        }));
+    }
+
+    #[test]
+    fn keyed_value() {
+        let dom_root = treexml::Document::parse(r#"<metrics>
+            <metric name="cpu" value="0.8"/>
+            <metric name="mem" value="0.5"/>
+            </metrics>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut keyed_value = HashMap::new();
+        keyed_value.insert("metric".to_string(), ("name".to_string(), "value".to_string()));
+        let cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            keyed_value,
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "metrics": { "cpu": 0.8, "mem": 0.5 }
+        }));
+    }
+
+    #[test]
+    fn keyed_value_child_collision() {
+        let mut cfg = Node2ObjectConfig::default();
+        cfg.keyed_value.insert("m".to_string(), ("name".to_string(), "value".to_string()));
+        let convert = |xml: &str, cfg: &Node2ObjectConfig| {
+            let dom_root = treexml::Document::parse(xml.as_bytes()).unwrap().root.unwrap();
+            (Value::Object(node2object_with(&dom_root, cfg)), try_node2object(&dom_root, cfg))
+        };
+
+        let (merged, _) = convert(r#"<r><x>1</x><m name="x" value="2"/><x>3</x></r>"#, &cfg);
+        assert_eq!(merged, json!({ "r": { "x": ["1", "2", "3"] } }));
+        let (merged, _) = convert(r#"<r><m name="x" value="2"/><x>1</x><x>3</x></r>"#, &cfg);
+        assert_eq!(merged, json!({ "r": { "x": ["2", "1", "3"] } }));
+
+        cfg.attribute_child_collision = AttributeChildCollision::Error;
+        let (merged, result) = convert(r#"<r><x>1</x><m name="x" value="2"/><x>3</x></r>"#, &cfg);
+        assert_eq!(merged, json!({ "r": { "x": ["1", "2", "3"] } }));
+        assert_eq!(result, Err(ConvertError::AttributeCollision { path: "r".into(), key: "x".into() }));
+    }
+
+    #[test]
+    fn strict_wellformed() {
+        // Literal duplicates never get past the parser.
+        assert!(treexml::Document::parse(r#"<a x="1" x="2"/>"#.as_bytes()).is_err());
+
+        let cfg = Node2ObjectConfig {
+            strict_wellformed: true,
+            ..Node2ObjectConfig::default()
+        };
+        let dom_root = treexml::Document::parse(r#"<user userId="1" user_id="2"/>"#.as_bytes()).unwrap().root.unwrap();
+        assert_eq!(try_node2object(&dom_root, &cfg), Err(ConvertError::DuplicateAttribute {
+            path: "user".to_string(),
+            attribute: "user_id".to_string(),
+        }));
+        assert!(node2object_with(&dom_root, &cfg).contains_key("user"));
+
+        let mut built = treexml::Element::new("list");
+        built.children.push(treexml::Element::new("2nd"));
+        assert_eq!(try_node2object(&built, &cfg), Err(ConvertError::InvalidName {
+            path: "list/2nd".to_string(),
+            name: "2nd".to_string(),
+        }));
+    }
+
+    #[test]
+    fn max_total_string_bytes() {
+        // 100 items of "item" (4 bytes, counted once) and 10 bytes of text each: 1004 bytes.
+        let mut list = treexml::Element::new("list");
+        for i in 0..100 {
+            let mut item = treexml::Element::new("item");
+            item.text = Some(format!("value-{:04}", i));
+            list.children.push(item);
+        }
+        let cfg = |max| Node2ObjectConfig {
+            max_total_string_bytes: Some(max),
+            ..Node2ObjectConfig::default()
+        };
+
+        assert!(try_node2object(&list, &cfg(1004)).is_ok());
+        assert_eq!(try_node2object(&list, &cfg(1003)), Err(ConvertError::StringBudgetExceeded {
+            path: "list/item".to_string(),
+            max: 1003,
+        }));
+        assert_eq!(node2object_with(&list, &cfg(1003))["list"]["item"].as_array().map(Vec::len), Some(100));
+    }
+
+    #[test]
+    fn entity_expansion() {
+        let lolz = r#"<?xml version="1.0"?>
+            <!DOCTYPE lolz [<!ENTITY lol "lol"><!ENTITY lol2 "&lol;&lol;&lol;&lol;">]>
+            <lolz>&lol2;</lolz>"#;
+        assert!(treexml::Document::parse(lolz.as_bytes()).is_err());
+
+        let xml = format!("<text>{}</text>", "&amp;&#x41;".repeat(50));
+        let dom_root = treexml::Document::parse(xml.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            max_total_string_bytes: Some(64),
+            ..Node2ObjectConfig::default()
+        };
+        assert_eq!(try_node2object(&dom_root, &cfg), Err(ConvertError::StringBudgetExceeded {
+            path: "text".to_string(),
+            max: 64,
+        }));
+    }
+
+    #[test]
+    fn dedup_arrays() {
+        let dom_root = treexml::Document::parse("<r><x>1</x><x>1</x><x>2</x><x>1</x></r>".as_bytes()).unwrap().root.unwrap();
+        let cfg = |dedup_mode| Node2ObjectConfig {
+            coerce_numbers: true,
+            dedup_arrays: ["x".to_string()].iter().cloned().collect(),
+            dedup_mode,
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg(DedupMode::Consecutive))), json!({
+            "r": { "x": [1, 2, 1] }
+        }));
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg(DedupMode::All))), json!({
+            "r": { "x": [1, 2] }
+        }));
+    }
+
+    #[test]
+    fn value_attribute() {
+        let dom_root = treexml::Document::parse(r#"<ref id="42" kind="user"/>"#.as_bytes()).unwrap().root.unwrap();
+        let mut value_attribute = HashMap::new();
+        value_attribute.insert("ref".to_string(), "id".to_string());
+        let cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            value_attribute,
+            value_attribute_conflict: ValueAttributeConflict::Error,
+            ..Node2ObjectConfig::default()
+        };
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({ "ref": 42 }));
+
+        let dom_root = treexml::Document::parse(r#"<ref id="42">Alex</ref>"#.as_bytes()).unwrap().root.unwrap();
+        assert_eq!(try_node2object(&dom_root, &cfg), Err(ConvertError::ValueAttributeConflict {
+            path: "ref".to_string(),
+            attribute: "id".to_string(),
+        }));
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({ "ref": { "id": 42, "text": "Alex" } }));
+    }
 
-#![feature(test)]
+    #[test]
+    fn number_parser() {
+        let dom_root = treexml::Document::parse("<r><n>42</n><f>42.0</f><e>1e5</e></r>".as_bytes()).unwrap().root.unwrap();
+        let cfg = |number_parser: Rc<dyn NumberParser>| Node2ObjectConfig {
+            coerce_numbers: true,
+            number_parser,
+            ..Node2ObjectConfig::default()
+        };
 
-extern crate treexml;
-extern crate inflector;
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg(Rc::new(DefaultNumberParser)))), json!({
+            "r": { "n": [42], "f": [42.0], "e": [100000.0] }
+        }));
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg(Rc::new(IntegerPreferring)))), json!({
+            "r": { "n": [42], "f": [42], "e": [100000] }
+        }));
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg(Rc::new(StringsOnly)))), json!({
+            "r": { "n": ["42"], "f": ["42.0"], "e": ["1e5"] }
+        }));
+    }
 
-#[macro_use]
-extern crate serde_json;
-extern crate test;
+    #[test]
+    fn namespace_mode() {
+        let dom_root = treexml::Document::parse(r#"<feed xmlns:atom="http://www.w3.org/2005/Atom">
+            <atom:title xml:lang="en">News</atom:title>
+            <atom:entry>First</atom:entry>
+            <entry>Local</entry>
+            </feed>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let convert = |namespace_mode| {
+            let cfg = Node2ObjectConfig {
+                namespace_mode,
+                ..Node2ObjectConfig::default()
+            };
+            Value::Object(node2object_with(&dom_root, &cfg))
+        };
 
-use serde_json::{Map, Number, Value};
-use inflector::cases::snakecase::to_snake_case;
+        assert_eq!(convert(NamespaceMode::Strip), json!({
+            "feed": { "title": [{ "xml_lang": "en", "text": "News" }], "entry": ["First", "Local"] }
+        }));
+        assert_eq!(convert(NamespaceMode::Keep), json!({
+            "feed": {
+                "atom:title": [{ "xml:lang": "en", "text": "News" }],
+                "atom:entry": ["First"],
+                "entry": ["Local"]
+            }
+        }));
+        assert_eq!(convert(NamespaceMode::Expand), json!({
+            "feed": {
+                "atom": { "title": [{ "xml:lang": "en", "text": "News" }], "entry": ["First"] },
+                "entry": ["Local"]
+            }
+        }));
+    }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum XMLNodeType {
-    Empty,
-    Text,
-    Attributes,
-    TextAndAttributes,
-    Parent,
-    SemiStructured,
-}
+    #[test]
+    fn bool_locale() {
+        let dom_root = treexml::Document::parse(r#"<compte statut="Non">
+            <actif>oui</actif>
+            <valide>vrai</valide>
+            </compte>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut bool_tokens = HashMap::new();
+        bool_tokens.insert("vrai".to_string(), true);
+        let cfg = Node2ObjectConfig {
+            bool_locale: Some("fr-CA".to_string()),
+            bool_tokens,
+            ..Node2ObjectConfig::default()
+        };
 
-fn scan_xml_node(e: &treexml::Element) -> XMLNodeType {
-    if e.children.is_empty() {
-        if e.text.is_none() && e.cdata.is_none() {
-            if e.attributes.is_empty() {
-                XMLNodeType::Empty
-            } else {
-                XMLNodeType::Attributes
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "compte": { "statut": false, "actif": [true], "valide": [true] }
+        }));
+    }
+
+    #[test]
+    fn annotate_counts() {
+        let dom_root = treexml::Document::parse("
+            <population>
+              <entry><name>Alex</name></entry>
+              <entry><name>Mel</name></entry>
+            </population>
+        ".as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            annotate_counts: true,
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "population": {
+                "entry": [
+                    { "name": ["Alex"], "name#count": 1 },
+                    { "name": ["Mel"], "name#count": 1 }
+                ],
+                "entry#count": 2
             }
-        } else {
-            if e.attributes.is_empty() {
-                XMLNodeType::Text
-            } else {
-                XMLNodeType::TextAndAttributes
+        }));
+    }
+
+    #[test]
+    fn keyed_duplicate_policy() {
+        let dom_root = treexml::Document::parse(r#"<props>
+            <prop key="a" value="1"/>
+            <prop key="a" value="2"/>
+            <prop key="b" value="3"/>
+            </props>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let cfg = |keyed_duplicate_policy| {
+            let mut keyed_value = HashMap::new();
+            keyed_value.insert("prop".to_string(), ("key".to_string(), "value".to_string()));
+            Node2ObjectConfig {
+                keyed_value,
+                keyed_duplicate_policy,
+                ..Node2ObjectConfig::default()
+            }
+        };
+        let convert = |policy| Value::Object(node2object_with(&dom_root, &cfg(policy)));
+
+        assert_eq!(convert(KeyedDuplicatePolicy::Array), json!({ "props": { "a": ["1", "2"], "b": "3" } }));
+        assert_eq!(convert(KeyedDuplicatePolicy::FirstWins), json!({ "props": { "a": "1", "b": "3" } }));
+        assert_eq!(convert(KeyedDuplicatePolicy::LastWins), json!({ "props": { "a": "2", "b": "3" } }));
+        assert_eq!(try_node2object(&dom_root, &cfg(KeyedDuplicatePolicy::Error)), Err(ConvertError::DuplicateKey {
+            path: "props".to_string(),
+            key: "a".to_string(),
+        }));
+    }
+
+    #[test]
+    fn coercion_chain() {
+        let dom_root = treexml::Document::parse("<r><x>1</x><y>2.5</y><z>yes</z></r>".as_bytes()).unwrap().root.unwrap();
+        let convert = |chain: &[JsonType]| {
+            let cfg = Node2ObjectConfig {
+                coercion_chain: Some(chain.to_vec()),
+                ..Node2ObjectConfig::default()
+            };
+            Value::Object(node2object_with(&dom_root, &cfg))
+        };
+
+        assert_eq!(convert(&[JsonType::Integer, JsonType::Bool, JsonType::Float]), json!({
+            "r": { "x": [1], "y": [2.5], "z": ["yes"] }
+        }));
+        assert_eq!(convert(&[JsonType::Bool, JsonType::Integer, JsonType::Float]), json!({
+            "r": { "x": [true], "y": [2.5], "z": ["yes"] }
+        }));
+        assert_eq!(convert(&[JsonType::String, JsonType::Integer]), json!({
+            "r": { "x": ["1"], "y": ["2.5"], "z": ["yes"] }
+        }));
+    }
+
+    #[test]
+    fn preserve_number_format() {
+        let dom_root = treexml::Document::parse(r#"<r id="+5"><code>007</code><n>5</n><price>1.50</price></r>"#.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            preserve_number_format: true,
+            number_parser: Rc::new(IntegerPreferring),
+            ..Node2ObjectConfig::default()
+        };
+        let data = node2object_with(&dom_root, &cfg);
+
+        assert_eq!(Value::Object(data.clone()), json!({
+            "r": {
+                "id": { "$raw": "+5" },
+                "code": [{ "$raw": "007" }],
+                "price": [{ "$raw": "1.50" }],
+                "n": [5]
+            }
+        }));
+        assert_eq!(object2node_with("r", data["r"].as_object().unwrap(), &cfg), dom_root);
+    }
+
+    #[test]
+    fn attribute_defaults() {
+        let dom_root = treexml::Document::parse(r#"<order>
+            <item id="1"/>
+            <item id="2" status="shipped"/>
+            <item>spare</item>
+            </order>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut defaults = HashMap::new();
+        defaults.insert("status".to_string(), json!("active"));
+        let mut attribute_defaults = HashMap::new();
+        attribute_defaults.insert("item".to_string(), defaults);
+        let cfg = Node2ObjectConfig {
+            attribute_defaults,
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "order": {
+                "item": [
+                    { "id": "1", "status": "active" },
+                    { "id": "2", "status": "shipped" },
+                    { "status": "active", "text": "spare" }
+                ]
+            }
+        }));
+    }
+
+    #[test]
+    fn numeric_output() {
+        let dom_root = treexml::Document::parse("<r><n>42</n><x>1.5</x><y>2.5</y></r>".as_bytes()).unwrap().root.unwrap();
+        let cfg = |numeric_output, fractional_int_policy| Node2ObjectConfig {
+            coerce_numbers: true,
+            number_parser: Rc::new(IntegerPreferring),
+            numeric_output: Some(numeric_output),
+            fractional_int_policy,
+            ..Node2ObjectConfig::default()
+        };
+        let convert = |output| Value::Object(node2object_with(&dom_root, &cfg(output, FractionalIntPolicy::Round)));
+
+        assert_eq!(convert(NumericOutput::AllFloat), json!({ "r": { "n": [42.0], "x": [1.5], "y": [2.5] } }));
+        assert_eq!(convert(NumericOutput::AllInt), json!({ "r": { "n": [42], "x": [2], "y": [3] } }));
+        assert_eq!(convert(NumericOutput::AllString), json!({ "r": { "n": ["42"], "x": ["1.5"], "y": ["2.5"] } }));
+        assert_eq!(
+            try_node2object(&dom_root, &cfg(NumericOutput::AllInt, FractionalIntPolicy::Error)),
+            Err(ConvertError::FractionalNumber {
+                path: "r/x/0".to_string(),
+                value: "1.5".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn attribute_prefix() {
+        let dom_root = treexml::Document::parse(r#"
+            <player score="9000"><name lang="en">Kolya</name></player>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            attribute_prefix: "@".into(),
+            text_key: "$text".into(),
+            ..Node2ObjectConfig::default()
+        };
+
+        let data = node2object_with(&dom_root, &cfg);
+        assert_eq!(Value::Object(data.clone()), json!({
+            "player": {
+                "@score": "9000",
+                "name": [{ "@lang": "en", "$text": "Kolya" }]
+            }
+        }));
+        assert_eq!(object2node_with("player", data["player"].as_object().unwrap(), &cfg), dom_root);
+    }
+
+    #[test]
+    fn document2object_empty_document() {
+        // treexml rejects empty input, so build the rootless document it would describe.
+        assert!(treexml::Document::parse("".as_bytes()).is_err());
+        let doc = treexml::Document::new();
+        let with = |empty_document| Node2ObjectConfig {
+            empty_document,
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(document2object(&doc, &with(EmptyDocument::EmptyMap)), Ok(json!({})));
+        assert_eq!(document2object(&doc, &with(EmptyDocument::Null)), Ok(Value::Null));
+        assert_eq!(
+            document2object(&doc, &with(EmptyDocument::Error)),
+            Err(ConvertError::EmptyDocument { path: String::new() })
+        );
+
+        let doc = treexml::Document::parse("<player>Kolya</player>".as_bytes()).unwrap();
+        assert_eq!(document2object(&doc, &with(EmptyDocument::Error)), Ok(json!({ "player": "Kolya" })));
+    }
+
+    #[test]
+    fn single_child_is_array() {
+        let one = treexml::Document::parse(r#"
+            <population><entry><name>Alex</name></entry></population>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let two = treexml::Document::parse(r#"
+            <population><entry><name>Alex</name></entry><entry><name>Mel</name></entry></population>
+        "#.as_bytes()).unwrap().root.unwrap();
+
+        assert_eq!(Value::Object(node2object(&one)), json!({
+            "population": { "entry": [{ "name": ["Alex"] }] }
+        }));
+        assert_eq!(Value::Object(node2object(&two)), json!({
+            "population": { "entry": [{ "name": ["Alex"] }, { "name": ["Mel"] }] }
+        }));
+
+        let cfg = Node2ObjectConfig {
+            collapse_single_element_arrays: true,
+            force_list: ["entry"].iter().map(|t| t.to_string()).collect(),
+            ..Node2ObjectConfig::default()
+        };
+        assert_eq!(Value::Object(node2object_with(&one, &cfg)), json!({
+            "population": { "entry": [{ "name": "Alex" }] }
+        }));
+        assert_eq!(Value::Object(node2object_with(&two, &cfg)), json!({
+            "population": { "entry": [{ "name": "Alex" }, { "name": "Mel" }] }
+        }));
+    }
+
+    #[test]
+    fn preserve_whitespace_tags() {
+        let dom_root = treexml::Document::parse("
+            <doc>
+              <p>  Call   it like this: </p>
+              <pre>
+    let data = node2object(root);
+</pre>
+            </doc>
+        ".as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            text_whitespace: TextWhitespace::Collapse,
+            preserve_whitespace_tags: ["pre".to_string()].iter().cloned().collect(),
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "doc": {
+                "p": ["Call it like this:"],
+                "pre": ["\n    let data = node2object(root);\n"]
+            }
+        }));
+    }
+
+    #[test]
+    fn auto_unwrap_scalar_lists() {
+        let dom_root = treexml::Document::parse(r#"
+            <palette>
+              <colors><c>red</c><c>green</c></colors>
+              <swatches><swatch><c>red</c></swatch></swatches>
+              <named id="warm"><c>orange</c></named>
+            </palette>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            auto_unwrap_scalar_lists: true,
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "palette": {
+                "colors": [["red", "green"]],
+                "swatches": [{ "swatch": [["red"]] }],
+                "named": [{ "id": "warm", "c": ["orange"] }]
+            }
+        }));
+
+        let colors = treexml::Document::parse("<colors><c>red</c><c>green</c></colors>".as_bytes()).unwrap().root.unwrap();
+        assert_eq!(Value::Object(node2object_with(&colors, &cfg)), json!({ "colors": ["red", "green"] }));
+    }
+
+    #[test]
+    fn mixed_content() {
+        let dom_root = treexml::Document::parse(r#"
+            <p lang="en">hello <b>world</b> goodbye</p>
+        "#.as_bytes()).unwrap().root.unwrap();
+
+        assert_eq!(Value::Object(node2object(&dom_root)), json!({
+            "p": { "lang": "en", "b": ["world"], "text": "hello  goodbye" }
+        }));
+    }
+
+    #[test]
+    fn key_case() {
+        let dom_root = treexml::Document::parse(r#"
+            <XMLData userID="7"><option>a</option></XMLData>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let with = |key_case| Node2ObjectConfig {
+            key_case,
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &with(KeyCase::SnakeCase))), json!({
+            "xml_data": { "user_id": "7", "option_tag": ["a"] }
+        }));
+        assert_eq!(Value::Object(node2object_with(&dom_root, &with(KeyCase::Verbatim))), json!({
+            "XMLData": { "userID": "7", "option": ["a"] }
+        }));
+        let upper = KeyCase::Custom(Rc::new(|name: &str| name.to_uppercase()));
+        assert_eq!(Value::Object(node2object_with(&dom_root, &with(upper))), json!({
+            "XMLDATA": { "USERID": "7", "OPTION": ["a"] }
+        }));
+    }
+
+    #[test]
+    fn snake_case_cache_is_bounded() {
+        let long = "LongName".repeat(SNAKE_CASE_CACHE_MAX_NAME);
+        assert_eq!(snake_case(&long), to_snake_case(&long));
+        for i in 0..2 * SNAKE_CASE_CACHE_LIMIT {
+            assert_eq!(snake_case(&format!("userID{}", i)), format!("user_id{}", i));
+        }
+        super::SNAKE_CASE_CACHE.with(|cache| {
+            let cache = cache.borrow();
+            assert!(cache.len() <= SNAKE_CASE_CACHE_LIMIT);
+            assert!(!cache.contains_key(&long));
+        });
+    }
+
+    #[test]
+    fn on_ambiguous_number() {
+        let dom_root = treexml::Document::parse("
+            <agent><code>007</code><age>42</age><year>1962</year></agent>
+        ".as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            on_ambiguous_number: Some(Rc::new(|text: &str, parsed: ParsedNumber| match parsed.risk {
+                NumberRisk::LeadingZero => Value::String(text.to_string()),
+                _ => parsed.value,
+            })),
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "agent": { "code": ["007"], "age": [42], "year": [1962] }
+        }));
+        assert_eq!(number_risk("1962"), None);
+        assert_eq!(number_risk("1962-05"), Some(NumberRisk::DateLike));
+        assert_eq!(number_risk("1e5"), Some(NumberRisk::Scientific));
+        assert_eq!(number_risk("12345678901234567"), Some(NumberRisk::Long));
+        assert_eq!(number_risk("0.5"), None);
+    }
+
+    #[test]
+    fn canonical_numbers_only() {
+        let dom_root = treexml::Document::parse("
+            <contact>
+              <zip>01234</zip>
+              <phone>000</phone>
+              <offset>+5</offset>
+              <price>1.50</price>
+              <floor>-3</floor>
+              <height>173.5</height>
+            </contact>
+        ".as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            canonical_numbers_only: true,
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "contact": {
+                "zip": ["01234"],
+                "phone": ["000"],
+                "offset": ["+5"],
+                "price": ["1.50"],
+                "floor": [-3],
+                "height": [173.5]
             }
+        }));
+
+        cfg.canonical_numbers_only = false;
+        let data = node2object_with(&dom_root, &cfg);
+        assert_eq!(data["contact"]["zip"], json!([1234]));
+        assert_eq!(data["contact"]["phone"], json!([0]));
+    }
+
+    #[test]
+    fn non_finite_numbers() {
+        let dom_root = treexml::Document::parse("
+            <r><x>NaN</x><x>inf</x><x>-inf</x><x>infinity</x><x>1.5</x></r>
+        ".as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "r": { "x": ["NaN", "inf", "-inf", "infinity", 1.5] }
+        }));
+        cfg.non_finite_numbers = NonFiniteNumbers::Null;
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "r": { "x": [null, null, null, null, 1.5] }
+        }));
+    }
+
+    #[test]
+    fn max_depth() {
+        let mut chain = treexml::Element::new("a");
+        chain.text = Some("bottom".into());
+        for _ in 1..50_000 {
+            let mut parent = treexml::Element::new("a");
+            parent.children.push(chain);
+            chain = parent;
         }
-    } else {
-        if e.text.is_some() || e.cdata.is_some() {
-            XMLNodeType::SemiStructured
-        } else {
-            XMLNodeType::Parent
+        let cfg = Node2ObjectConfig {
+            max_depth: Some(3),
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(
+            try_node2object(&chain, &cfg),
+            Err(ConvertError::TooDeep { path: "a/a/a/a".into(), max: 3 })
+        );
+        assert_eq!(Value::Object(node2object_with(&chain, &cfg)), json!({
+            "a": { "a": [{ "a": [{ "a": [{ "#truncated": true }] }] }] }
+        }));
+
+        let path = vec!["a"; DEFAULT_MAX_DEPTH + 1].join("/");
+        assert_eq!(
+            try_node2object(&chain, &Node2ObjectConfig::default()),
+            Err(ConvertError::TooDeep { path, max: DEFAULT_MAX_DEPTH })
+        );
+        assert_eq!(node2object(&chain).len(), 1);
+
+        // Dropping the chain recursively would overflow the stack as well.
+        let mut pending = vec![chain];
+        while let Some(mut e) = pending.pop() {
+            pending.append(&mut e.children);
         }
     }
-}
 
-fn parse_text(text: &str) -> Value {
-    Value::String(text.into())
-}
+    #[test]
+    fn convert_error_element() {
+        let dom_root = treexml::Document::parse(r#"
+            <html><head><link href="a.css"/></head></html>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            required_attributes: [("link".to_string(), vec!["rel".to_string()])].iter().cloned().collect(),
+            ..Node2ObjectConfig::default()
+        };
 
-fn parse_text_contents(e: &treexml::Element) -> Value {
-    let text = format!(
-        "{}{}",
-        &e.text.clone().unwrap_or(String::new()),
-        &e.cdata.clone().unwrap_or(String::new())
-    );
-    parse_text(&text)
-}
+        let err = try_node2object(&dom_root, &cfg).unwrap_err();
+        assert_eq!(err.path(), "html/head/link");
+        assert_eq!(err.element(), "link");
+        assert_eq!(err.to_string(), "html/head/link: missing required attribute `rel`");
+        assert_eq!(ConvertError::EmptyDocument { path: String::new() }.element(), "");
+    }
 
-fn convert_node_aux(e: &treexml::Element) -> Option<Value> {
-    match scan_xml_node(e) {
-        XMLNodeType::Parent => {
-            let mut data = Map::new();
-            let mut vectorized = std::collections::HashSet::new();
+    #[test]
+    fn collapse_single_element_arrays() {
+        let dom_root = treexml::Document::parse(r#"
+            <population>
+              <entry><name>Alex</name><tag>a</tag><tag>b</tag></entry>
+              <entry><name>Mel</name></entry>
+              <updated>2020</updated>
+            </population>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig {
+            collapse_single_element_arrays: true,
+            ..Node2ObjectConfig::default()
+        };
 
-            if e.attributes.len() > 0 {
-                for (k, v) in e.attributes.clone().into_iter() {
-                    data.insert(to_snake_case(&k), parse_text(&v));
-                }
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "population": {
+                "entry": [{ "name": "Alex", "tag": ["a", "b"] }, { "name": "Mel" }],
+                "updated": "2020"
             }
-            for c in &e.children {
-                match convert_node_aux(c) {
-                    Some(v) => {
-                        let snake_cased_name = to_snake_case(&c.name);
-                        use std::str::FromStr;
-                        let snake_cased_name = if snake_cased_name.eq("option") {
-                            "option_tag".to_string()
-                        } else {
-                            snake_cased_name
-                        };
-                            if !vectorized.contains(&snake_cased_name) {
-                                data.insert(snake_cased_name.clone(), Value::Array(vec![v]));
-                                vectorized.insert(snake_cased_name);
-                            } else {
-                                data.get_mut(&snake_cased_name)
-                                    .unwrap()
-                                    .as_array_mut()
-                                    .unwrap()
-                                    .push(v);
-                            }
-                    }
-                    _ => {}
-                }
+        }));
+
+        cfg.force_list.insert("name".to_string());
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "population": {
+                "entry": [{ "name": ["Alex"], "tag": ["a", "b"] }, { "name": ["Mel"] }],
+                "updated": "2020"
             }
-            Some(Value::Object(data))
-        }
-        XMLNodeType::Text => Some(parse_text_contents(e)),
-        XMLNodeType::Attributes => Some(Value::Object(
-            e.attributes
-                .clone()
-                .into_iter()
-                .map(|(k, v)| (to_snake_case(&k), parse_text(&v)))
-                .collect(),
-        )),
-        XMLNodeType::TextAndAttributes => Some(Value::Object(
-            e.attributes
-                .clone()
-                .into_iter()
-                .map(|(k, v)| (to_snake_case(&k), parse_text(&v)))
-                .chain(vec![("text".to_string(), parse_text_contents(&e))])
-                .collect(),
-        )),
-        _ => None,
+        }));
     }
-}
 
-/// Converts treexml::Element into a serde_json hashmap. The latter can be wrapped in Value::Object.
-pub fn node2object(e: &treexml::Element) -> Map<String, Value> {
-    let mut data = Map::new();
-    data.insert(to_snake_case(&e.name), convert_node_aux(e).unwrap_or(Value::Null));
-    data
-}
+    #[test]
+    fn empty_element() {
+        let dom_root = treexml::Document::parse("<r><e/><f>1</f></r>".as_bytes()).unwrap().root.unwrap();
+        let empty_root = treexml::Element::new("e");
+        let with = |empty_element| Node2ObjectConfig {
+            empty_element,
+            ..Node2ObjectConfig::default()
+        };
+        let convert = |e, empty_element| Value::Object(node2object_with(e, &with(empty_element)));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use test::Bencher;
+        assert_eq!(convert(&dom_root, EmptyRepr::Null), json!({ "r": { "f": ["1"] } }));
+        assert_eq!(convert(&empty_root, EmptyRepr::Null), json!({ "e": null }));
+        assert_eq!(convert(&dom_root, EmptyRepr::EmptyString), json!({ "r": { "e": [""], "f": ["1"] } }));
+        assert_eq!(convert(&empty_root, EmptyRepr::EmptyString), json!({ "e": "" }));
+        assert_eq!(convert(&dom_root, EmptyRepr::EmptyObject), json!({ "r": { "e": [{}], "f": ["1"] } }));
+        assert_eq!(convert(&empty_root, EmptyRepr::EmptyObject), json!({ "e": {} }));
+        assert_eq!(convert(&dom_root, EmptyRepr::Omit), json!({ "r": { "f": ["1"] } }));
+        assert_eq!(convert(&empty_root, EmptyRepr::Omit), json!({}));
+    }
 
-    #[bench]
-    fn bench(b: &mut Bencher) {
-        let xml = include_str!("../examples/xml.xml");
-        b.iter(|| {
-            let n = test::black_box(10);
-            (0..n).for_each(|_| {
-                let xml = treexml::Document::parse(xml.as_bytes()).unwrap();
-                let xml = xml.root.unwrap();
-                let _ = node2object(&xml);
-            })
-        })
+    #[test]
+    fn preserve_cdata() {
+        let dom_root = treexml::Document::parse(r#"
+            <r><x>12<![CDATA[a<b]]></x><y><![CDATA[c]]></y><z n="1">3</z></r>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig::default();
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "r": { "x": ["12a<b"], "y": ["c"], "z": [{ "n": "1", "text": "3" }] }
+        }));
+        cfg.preserve_cdata = true;
+        let data = node2object_with(&dom_root, &cfg);
+        assert_eq!(Value::Object(data.clone()), json!({
+            "r": {
+                "x": [{ "text": "12", "#cdata": "a<b" }],
+                "y": [{ "#cdata": "c" }],
+                "z": [{ "n": "1", "text": "3" }]
+            }
+        }));
+        assert_eq!(object2node_with("r", data["r"].as_object().unwrap(), &cfg), dom_root);
     }
 
     #[test]
-    fn smart_list_detection() {
-        let raw_xml = treexml::Document::parse(r#"<a>
-            <b first="1"/>
-            <b first="2"/>
-            <c first="3"/>
-            </a>
+    fn value_override() {
+        let dom_root = treexml::Document::parse(r#"
+            <event><flags>urgent,internal</flags><name kind="a">Launch</name><count>3</count></event>
         "#.as_bytes()).unwrap().root.unwrap();
-        let actual = Value::Object(node2object(&raw_xml));
-        assert_eq!(actual, json!({
-            "a": {
-                "b": [ { "first": "1" }, { "first": "2" } ],
-                "c": [ { "first": "3" } ]
+        let cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            value_override: Some(Rc::new(|path: &str, text: &str| match path {
+                "event/flags" => Some(Value::Array(text.split(',').map(|f| Value::String(f.into())).collect())),
+                "event/name" => Some(Value::String(text.to_uppercase())),
+                _ => None,
+            })),
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "event": {
+                "flags": [["urgent", "internal"]],
+                "name": [{ "kind": "a", "text": "LAUNCH" }],
+                "count": [3]
             }
         }));
     }
 
+    #[test]
+    fn attribute_child_collision() {
+        let dom_root = treexml::Document::parse(r#"
+            <item id="1" name="a"><id>2</id><id>3</id></item>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig::default();
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "item": { "id": ["1", "2", "3"], "name": "a" }
+        }));
+
+        cfg.attribute_child_collision = AttributeChildCollision::Error;
+        assert_eq!(
+            try_node2object(&dom_root, &cfg),
+            Err(ConvertError::AttributeCollision { path: "item".into(), key: "id".into() })
+        );
+        assert!(node2object_with(&dom_root, &cfg)["item"]["id"].as_array().is_some_and(|ids| ids.len() == 3));
+
+        cfg.attribute_prefix = "@".into();
+        assert_eq!(Value::Object(try_node2object(&dom_root, &cfg).unwrap()), json!({
+            "item": { "@id": "1", "@name": "a", "id": ["2", "3"] }
+        }));
+    }
+
+    #[test]
+    fn node2object_at_path() {
+        let dom_root = treexml::Document::parse("
+            <Population>
+              <Entry><Name>Alex</Name></Entry>
+              <Entry><Name>Mel</Name></Entry>
+            </Population>
+        ".as_bytes()).unwrap().root.unwrap();
+
+        assert_eq!(
+            node2object_at(&dom_root, &["population", "entry"]).map(Value::Object),
+            Some(json!({ "entry": { "name": ["Alex"] } }))
+        );
+        assert_eq!(
+            node2object_at(&dom_root, &["population", "entry", "name"]).map(Value::Object),
+            Some(json!({ "name": "Alex" }))
+        );
+        assert_eq!(node2object_at(&dom_root, &["population", "missing"]), None);
+        assert_eq!(node2object_at(&dom_root, &["Population"]), None);
+    }
+
+    #[test]
+    fn key_remap() {
+        let dom_root = treexml::Document::parse(r#"
+            <field type="int"><type>u32</type><option>a</option><class>b</class></field>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let mut cfg = Node2ObjectConfig::default();
+        cfg.key_remap.insert("type".to_string(), "type_tag".to_string());
+
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "field": { "type": "int", "type_tag": ["u32"], "option_tag": ["a"], "class": ["b"] }
+        }));
+        let verbatim = Node2ObjectConfig { key_case: KeyCase::Verbatim, ..Node2ObjectConfig::default() };
+        assert_eq!(Value::Object(node2object_with(&dom_root, &verbatim)), json!({
+            "field": { "type": ["int", "u32"], "option": ["a"], "class": ["b"] }
+        }));
+        cfg.key_remap.clear();
+        assert_eq!(Value::Object(node2object_with(&dom_root, &cfg)), json!({
+            "field": { "type": ["int", "u32"], "option": ["a"], "class": ["b"] }
+        }));
+    }
+
+    /// The conventions the original tests below were written against: verbatim keys, `@` on
+    /// attributes, `#text`, numbers coerced to floats and single children unwrapped.
+    fn original_conventions() -> Node2ObjectConfig {
+        Node2ObjectConfig {
+            key_case: KeyCase::Verbatim,
+            coerce_numbers: true,
+            coerce_attributes: true,
+            numeric_output: Some(NumericOutput::AllFloat),
+            text_key: "#text".to_string(),
+            attribute_prefix: "@".to_string(),
+            collapse_single_element_arrays: true,
+            ..Node2ObjectConfig::default()
+        }
+    }
+
     #[test]
     fn node2object_empty() {
         let fixture = treexml::Element::new("e");
         let scan_result = XMLNodeType::Empty;
         let conv_result = json!({ "e": null });
 
-        assert_eq!(scan_result, scan_xml_node(&fixture));
+        assert_eq!(scan_result, scan_xml_node(&fixture, EmptyDetection::Strict));
         assert_eq!(conv_result, Value::Object(node2object(&fixture)));
     }
 
@@ -211,7 +2930,7 @@ mod tests {
         let scan_result = XMLNodeType::Text;
         let conv_result = json!({"player": "Kolya"});
 
-        assert_eq!(scan_result, scan_xml_node(&fixture));
+        assert_eq!(scan_result, scan_xml_node(&fixture, EmptyDetection::Strict));
         assert_eq!(conv_result, Value::Object(node2object(&fixture)));
     }
 
@@ -222,8 +2941,8 @@ mod tests {
         let scan_result = XMLNodeType::Attributes;
         let conv_result = json!({ "player": json!({"@score": 9000.0}) });
 
-        assert_eq!(scan_result, scan_xml_node(&fixture));
-        assert_eq!(conv_result, Value::Object(node2object(&fixture)));
+        assert_eq!(scan_result, scan_xml_node(&fixture, EmptyDetection::Strict));
+        assert_eq!(conv_result, Value::Object(node2object_with(&fixture, &original_conventions())));
     }
 
     #[test]
@@ -234,8 +2953,8 @@ mod tests {
         let scan_result = XMLNodeType::TextAndAttributes;
         let conv_result = json!({ "player": json!({"#text": "Kolya", "@score": 9000.0}) });
 
-        assert_eq!(scan_result, scan_xml_node(&fixture));
-        assert_eq!(conv_result, Value::Object(node2object(&fixture)));
+        assert_eq!(scan_result, scan_xml_node(&fixture, EmptyDetection::Strict));
+        assert_eq!(conv_result, Value::Object(node2object_with(&fixture, &original_conventions())));
     }
 
     #[test]
@@ -262,8 +2981,8 @@ mod tests {
         let conv_result =
             json!({ "ServerData": json!({ "Player": [ "Kolya", "Petya", "Misha" ] }) });
 
-        assert_eq!(scan_result, scan_xml_node(&fixture));
-        assert_eq!(conv_result, Value::Object(node2object(&fixture)));
+        assert_eq!(scan_result, scan_xml_node(&fixture, EmptyDetection::Strict));
+        assert_eq!(conv_result, Value::Object(node2object_with(&fixture, &original_conventions())));
     }
 
     #[test]
@@ -281,7 +3000,7 @@ mod tests {
             .root
             .unwrap();
 
-        let json_result = Value::Object(node2object(&dom_root));
+        let json_result = Value::Object(node2object_with(&dom_root, &original_conventions()));
         let expected = json!({
             "a": json!({
                 "@pizza": "hotdog",
@@ -293,4 +3012,20 @@ mod tests {
         });
         assert_eq!(json_result, expected);
     }
+
+    #[test]
+    fn node2object_default_output() {
+        let dom_root = treexml::Document::parse(r#"
+            <ServerData score="9000"><Player>Kolya</Player><Player>Petya</Player>
+            <b frenchfry="milkshake">fries</b></ServerData>
+        "#.as_bytes()).unwrap().root.unwrap();
+
+        assert_eq!(Value::Object(node2object(&dom_root)), json!({
+            "server_data": {
+                "score": "9000",
+                "player": ["Kolya", "Petya"],
+                "b": [{ "frenchfry": "milkshake", "text": "fries" }]
+            }
+        }));
+    }
 }
@@ -0,0 +1,125 @@
+//! Pluggable parsing of numeric text, see `Node2ObjectConfig::number_parser`.
+
+use serde_json::{Number, Value};
+
+/// Turns numeric-looking text into a JSON value. `text` has already been normalized by the
+/// separator settings, so it uses `.` as the decimal separator and has no digit grouping.
+/// Returning `None` keeps the text as a string.
+pub trait NumberParser {
+    fn parse(&self, text: &str) -> Option<Value>;
+}
+
+/// Why a parsed number might have been meant as a string, see `on_ambiguous_number`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumberRisk {
+    /// The text looks like a date or time with separators or a zone, such as `2020-05` or
+    /// `12:30`. Bare years such as `2020` are not flagged.
+    DateLike,
+    /// The text uses scientific notation, such as `1e5`.
+    Scientific,
+    /// The integer part has a leading zero, such as `007`.
+    LeadingZero,
+    /// The text has more digits than an `f64` holds exactly.
+    Long,
+}
+
+/// A number found by the `number_parser` in text with a `NumberRisk`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedNumber {
+    /// The value the number converts to.
+    pub value: Value,
+    pub risk: NumberRisk,
+}
+
+fn parse_f64(text: &str) -> Option<Value> {
+    text.parse::<f64>().ok().and_then(Number::from_f64).map(Value::Number)
+}
+
+fn parse_integer(text: &str) -> Option<Value> {
+    text.parse::<i64>()
+        .map(Number::from)
+        .or_else(|_| text.parse::<u64>().map(Number::from))
+        .ok()
+        .map(Value::Number)
+}
+
+/// Parses numbers written without a fractional part or exponent as integers if they fit
+/// `i64` or `u64`, and everything else as `f64` (the default): `42` gives `42`, while `42.0`,
+/// `173.5` and `1e5` give floats.
+pub struct DefaultNumberParser;
+
+impl NumberParser for DefaultNumberParser {
+    fn parse(&self, text: &str) -> Option<Value> {
+        if text.contains(['.', 'e', 'E']) {
+            parse_f64(text)
+        } else {
+            parse_integer(text).or_else(|| parse_f64(text))
+        }
+    }
+}
+
+/// Parses every number with an integral value as an integer if it fits `i64` or `u64`, even
+/// when written with a fractional part or exponent, and everything else as `f64`: `42`,
+/// `42.0` and `1e5` give integers, while `4.5` gives a float.
+pub struct IntegerPreferring;
+
+impl NumberParser for IntegerPreferring {
+    fn parse(&self, text: &str) -> Option<Value> {
+        parse_integer(text).or_else(|| {
+            let v = text.parse::<f64>().ok()?;
+            if v.fract() == 0.0 && v.abs() < 9.2e18 {
+                Some(Value::Number((v as i64).into()))
+            } else {
+                parse_f64(text)
+            }
+        })
+    }
+}
+
+/// Never parses numbers, so numeric text stays a string.
+pub struct StringsOnly;
+
+impl NumberParser for StringsOnly {
+    fn parse(&self, _text: &str) -> Option<Value> {
+        None
+    }
+}
+
+/// Never loses digits: integers that fit `i64` or `u64` become integers, other numbers
+/// become `f64` if that reproduces the text exactly, and numbers `f64` cannot hold exactly
+/// (`0.1000000000000000055`, 30-digit integers) stay strings.
+pub struct ArbitraryPrecision;
+
+impl NumberParser for ArbitraryPrecision {
+    fn parse(&self, text: &str) -> Option<Value> {
+        if let Some(v) = parse_integer(text) {
+            return Some(v);
+        }
+        let v = text.parse::<f64>().ok()?;
+        if v.to_string() == text.trim_start_matches('+') {
+            parse_f64(text)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_parsers() {
+        assert_eq!(DefaultNumberParser.parse("42"), Some(json!(42)));
+        assert_eq!(DefaultNumberParser.parse("42.0"), Some(json!(42.0)));
+        assert_eq!(DefaultNumberParser.parse("1e5"), Some(json!(1e5)));
+        assert_eq!(DefaultNumberParser.parse("18446744073709551616"), Some(json!(18446744073709551616.0)));
+        assert_eq!(IntegerPreferring.parse("42.0"), Some(json!(42)));
+        assert_eq!(IntegerPreferring.parse("1e3"), Some(json!(1000)));
+        assert_eq!(DefaultNumberParser.parse("1e3"), Some(json!(1000.0)));
+        assert_eq!(IntegerPreferring.parse("4.5"), Some(json!(4.5)));
+        assert_eq!(StringsOnly.parse("42"), None);
+        assert_eq!(ArbitraryPrecision.parse("18446744073709551616"), None);
+        assert_eq!(ArbitraryPrecision.parse("0.25"), Some(json!(0.25)));
+    }
+}
@@ -0,0 +1,110 @@
+//! Navigation helpers for converted output.
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use {Node2ObjectConfig, CDATA_KEY};
+
+/// Output converted with the given settings, which can tell attributes apart from child
+/// elements.
+///
+/// Attribute keys start with `attribute_prefix`. Without a prefix, attributes are told apart
+/// by their values: attributes convert to scalars and child elements to arrays (or objects).
+/// A child that `collapse_single_element_arrays` stores as a bare scalar then looks like an
+/// attribute, so set a prefix when collapsing. The element text stored under `text_key` (and
+/// CDATA kept by `preserve_cdata`) belongs to neither.
+#[derive(Clone)]
+pub struct XmlJson<'c>(pub Value, pub &'c Node2ObjectConfig);
+
+impl<'c> fmt::Debug for XmlJson<'c> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("XmlJson").field(&self.0).finish()
+    }
+}
+
+impl<'c> XmlJson<'c> {
+    fn is_attribute(&self, key: &str, v: &Value) -> bool {
+        if self.1.attribute_prefix.is_empty() {
+            !v.is_array() && !v.is_object()
+        } else {
+            key.starts_with(&self.1.attribute_prefix)
+        }
+    }
+
+    fn entries(&self, pointer: &str, attributes: bool) -> Map<String, Value> {
+        let cfg = self.1;
+        match self.0.pointer(pointer) {
+            Some(Value::Object(obj)) => obj.iter()
+                .filter(|&(k, _)| *k != cfg.text_key && !(cfg.preserve_cdata && k == CDATA_KEY))
+                .filter(|&(k, v)| self.is_attribute(k, v) == attributes)
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            _ => Map::new(),
+        }
+    }
+
+    /// Attributes of the element at the JSON pointer `pointer`, e.g. `/a/b/0`, keyed like in
+    /// the output. Empty if the pointer does not resolve to an element object.
+    pub fn attributes(&self, pointer: &str) -> Map<String, Value> {
+        self.entries(pointer, true)
+    }
+
+    /// Child elements of the element at the JSON pointer `pointer`, keyed like in the output.
+    pub fn children(&self, pointer: &str) -> Map<String, Value> {
+        self.entries(pointer, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use treexml;
+    use {node2object, node2object_with};
+
+    #[test]
+    fn attributes_and_children() {
+        let dom_root = treexml::Document::parse(
+            "
+        <a pizza=\"hotdog\">
+          <b frenchfry=\"milkshake\">
+            <c>scotch</c>
+          </b>
+        </a>
+    "
+                .as_bytes(),
+        ).unwrap()
+            .root
+            .unwrap();
+        let cfg = Node2ObjectConfig::default();
+        let doc = XmlJson(Value::Object(node2object(&dom_root)), &cfg);
+
+        assert_eq!(Value::Object(doc.attributes("/a")), json!({ "pizza": "hotdog" }));
+        assert_eq!(Value::Object(doc.children("/a")).pointer("/b/0/frenchfry"), Some(&json!("milkshake")));
+        assert_eq!(Value::Object(doc.attributes("/a/b/0")), json!({ "frenchfry": "milkshake" }));
+        assert_eq!(Value::Object(doc.children("/a/b/0")), json!({ "c": ["scotch"] }));
+        assert!(doc.attributes("/a/missing").is_empty());
+    }
+
+    #[test]
+    fn prefixed_attributes() {
+        let dom_root = treexml::Document::parse(r#"
+            <a pizza="hotdog"><b frenchfry="milkshake">fries</b><c>scotch</c></a>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            attribute_prefix: "@".to_string(),
+            text_key: "$text".to_string(),
+            collapse_single_element_arrays: true,
+            ..Node2ObjectConfig::default()
+        };
+        let doc = XmlJson(Value::Object(node2object_with(&dom_root, &cfg)), &cfg);
+
+        assert_eq!(Value::Object(doc.attributes("/a")), json!({ "@pizza": "hotdog" }));
+        assert_eq!(Value::Object(doc.children("/a")), json!({
+            "b": { "@frenchfry": "milkshake", "$text": "fries" },
+            "c": "scotch"
+        }));
+        assert_eq!(Value::Object(doc.attributes("/a/b")), json!({ "@frenchfry": "milkshake" }));
+        assert!(doc.children("/a/b").is_empty());
+    }
+}
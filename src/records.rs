@@ -0,0 +1,179 @@
+//! Helpers for documents that are mostly a list of similar records.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{SendError, SyncSender};
+
+use serde_json::Value;
+use treexml;
+
+use {convert_node_aux, element_key, root_key, tag_name, Context, Node2ObjectConfig};
+
+/// Elements at a slash-separated path of output keys starting with the root, in document order.
+/// The path is followed one level at a time, so its length is not limited by the stack.
+pub(crate) fn find_elements<'e>(e: &'e treexml::Element, item_path: &str, cfg: &Node2ObjectConfig) -> Vec<&'e treexml::Element> {
+    let mut path = item_path.split('/').filter(|s| !s.is_empty());
+    let mut items = match path.next() {
+        Some(root) if root_key(e, cfg) == root => vec![e],
+        _ => return Vec::new(),
+    };
+    for head in path {
+        items = items
+            .iter()
+            .flat_map(|e| &e.children)
+            .filter(|c| tag_name(c, cfg).is_some_and(|name| element_key(c, &name, cfg) == head))
+            .collect();
+    }
+    items
+}
+
+/// Finds the elements at `item_path` and converts each one on its own.
+///
+/// `item_path` is a slash-separated list of keys as they appear in the output, starting
+/// with the root, e.g. `population/entry`. Every element matching the path is returned in
+/// document order; empty elements that would be left out of their parent are skipped.
+pub fn extract_records(e: &treexml::Element, item_path: &str, cfg: &Node2ObjectConfig) -> Vec<Value> {
+    let mut ctx = Context::new(cfg, false);
+    ctx.index_ids(e);
+    find_elements(e, item_path, cfg)
+        .into_iter()
+        .filter_map(|item| convert_node_aux(item, &tag_name(item, cfg)?, &mut ctx).unwrap_or(None))
+        .collect()
+}
+
+/// Converts the records at `item_path` (see `extract_records`) into newline-delimited JSON:
+/// one standalone JSON document per line, each line terminated by `\n`.
+pub fn to_ndjson(e: &treexml::Element, item_path: &str, cfg: &Node2ObjectConfig) -> String {
+    let mut out = String::new();
+    for record in extract_records(e, item_path, cfg) {
+        out.push_str(&record.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+/// Text of a record's `field`, looking through the one-element list a child converts to.
+fn field_text(record: &Value, field: &str) -> Option<String> {
+    let value = match record.get(field)? {
+        Value::Array(items) if items.len() == 1 => &items[0],
+        value => value,
+    };
+    match *value {
+        Value::String(ref s) => Some(s.clone()),
+        Value::Number(ref n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Groups the records at `item_path` (see `extract_records`) by the value of `key_field`, an
+/// attribute or child of each record. Records lacking it, or where it is not a scalar, are
+/// grouped under `missing`. Records keep their document order within a group.
+pub fn partition(
+    e: &treexml::Element,
+    item_path: &str,
+    key_field: &str,
+    missing: &str,
+    cfg: &Node2ObjectConfig,
+) -> HashMap<String, Vec<Value>> {
+    let mut groups: HashMap<String, Vec<Value>> = HashMap::new();
+    for record in extract_records(e, item_path, cfg) {
+        let key = field_text(&record, key_field).unwrap_or_else(|| missing.to_string());
+        groups.entry(key).or_default().push(record);
+    }
+    groups
+}
+
+/// Like `convert_to_channel`, using the given settings.
+pub fn convert_to_channel_with(
+    e: &treexml::Element,
+    cfg: &Node2ObjectConfig,
+    tx: &SyncSender<(String, Value)>,
+) -> Result<(), SendError<(String, Value)>> {
+    let mut ctx = Context::new(cfg, false);
+    ctx.index_ids(e);
+    for c in &e.children {
+        let name = match tag_name(c, cfg) {
+            Some(name) => name,
+            None => continue,
+        };
+        if let Ok(Some(v)) = convert_node_aux(c, &name, &mut ctx) {
+            tx.send((element_key(c, &name, cfg), v))?;
+        }
+    }
+    Ok(())
+}
+
+/// Converts the children of `e` one at a time, sending each as a `(key, value)` pair as soon as
+/// it is ready. A bounded channel makes conversion wait for a slow consumer instead of
+/// buffering the whole document. Stops early if the receiver hangs up.
+pub fn convert_to_channel(e: &treexml::Element, tx: &SyncSender<(String, Value)>) -> Result<(), SendError<(String, Value)>> {
+    convert_to_channel_with(e, &Node2ObjectConfig::default(), tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::sync_channel;
+    use std::thread;
+
+    #[test]
+    fn population_to_ndjson() {
+        let dom_root = treexml::Document::parse("
+            <population>
+              <entry>
+                <name>Alex</name>
+                <height>173.5</height>
+              </entry>
+              <entry>
+                <name>Mel</name>
+                <height>180.4</height>
+              </entry>
+            </population>
+        ".as_bytes()).unwrap().root.unwrap();
+        let out = to_ndjson(&dom_root, "population/entry", &Node2ObjectConfig::default());
+        let lines: Vec<Value> = out.lines().map(|l| ::serde_json::from_str(l).unwrap()).collect();
+
+        assert!(out.ends_with('\n'));
+        assert_eq!(lines, vec![
+            json!({ "name": ["Alex"], "height": ["173.5"] }),
+            json!({ "name": ["Mel"], "height": ["180.4"] }),
+        ]);
+    }
+
+    #[test]
+    fn partition_by_region() {
+        let dom_root = treexml::Document::parse(r#"
+            <population>
+              <entry region="north"><name>Alex</name></entry>
+              <entry><name>Mel</name><region>south</region></entry>
+              <entry region="north"><name>Kim</name></entry>
+              <entry><name>Sam</name></entry>
+            </population>
+        "#.as_bytes()).unwrap().root.unwrap();
+        let groups = partition(&dom_root, "population/entry", "region", "unknown", &Node2ObjectConfig::default());
+
+        assert_eq!(groups.len(), 3);
+        assert_eq!(Value::Array(groups["north"].clone()), json!([
+            { "region": "north", "name": ["Alex"] },
+            { "region": "north", "name": ["Kim"] }
+        ]));
+        assert_eq!(Value::Array(groups["south"].clone()), json!([{ "name": ["Mel"], "region": ["south"] }]));
+        assert_eq!(Value::Array(groups["unknown"].clone()), json!([{ "name": ["Sam"] }]));
+    }
+
+    #[test]
+    fn bounded_channel() {
+        let xml = include_str!("../examples/xml.xml");
+        let dom_root = treexml::Document::parse(xml.as_bytes()).unwrap().root.unwrap();
+        let (tx, rx) = sync_channel(1);
+        let consumer = thread::spawn(move || rx.iter().collect::<Vec<(String, Value)>>());
+
+        convert_to_channel(&dom_root, &tx).unwrap();
+        drop(tx);
+        let records = consumer.join().unwrap();
+
+        assert_eq!(records.len(), dom_root.children.len());
+        assert!(records.iter().all(|(key, _)| key == "entry"));
+        assert_eq!(records[0].1["name"], json!(["Alex"]));
+    }
+}
@@ -0,0 +1,126 @@
+//! Conversion from JSON objects back into XML nodes.
+
+use serde_json::{Map, Value};
+use treexml;
+
+use {Node2ObjectConfig, CDATA_KEY, NUM_KEY, RAW_KEY};
+
+/// Source text of a number written by `dual_number` or `preserve_number_format`.
+fn number_text(obj: &Map<String, Value>) -> Option<String> {
+    if obj.keys().all(|k| k == NUM_KEY || k == RAW_KEY) {
+        obj.get(RAW_KEY).and_then(Value::as_str).map(str::to_string)
+    } else {
+        None
+    }
+}
+
+fn scalar_text(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Object(obj) => number_text(obj),
+        _ => None,
+    }
+}
+
+/// Appends the elements named `name` that `v` describes. Arrays expand into one element per
+/// item, mirroring how repeated children are vectorized.
+fn push_elements(name: &str, v: &Value, cfg: &Node2ObjectConfig, out: &mut Vec<treexml::Element>) {
+    match v {
+        Value::Array(items) => for item in items {
+            push_elements(name, item, cfg, out);
+        },
+        Value::Object(obj) if number_text(obj).is_none() => out.push(object2node_with(name, obj, cfg)),
+        _ => {
+            let mut e = treexml::Element::new(name);
+            e.text = scalar_text(v);
+            out.push(e);
+        }
+    }
+}
+
+/// Builds an element named `root_name` from the contents of a converted element, using the
+/// same settings it was converted with. The `text_key` entry becomes the element's text,
+/// arrays and objects become child elements and the remaining scalars become attributes.
+/// With `preserve_cdata`, the `#cdata` entry becomes the element's CDATA.
+/// With an `attribute_prefix`, only scalars under prefixed keys become attributes, and other
+/// scalars become child elements holding them as text. Numbers written by `dual_number` or
+/// `preserve_number_format` count as scalars and get their source text back.
+pub fn object2node_with(root_name: &str, obj: &Map<String, Value>, cfg: &Node2ObjectConfig) -> treexml::Element {
+    let mut e = treexml::Element::new(root_name);
+    if let Some(text) = number_text(obj) {
+        e.text = Some(text);
+        return e;
+    }
+    for (k, v) in obj {
+        let attribute = if cfg.attribute_prefix.is_empty() {
+            Some(k.as_str())
+        } else {
+            k.strip_prefix(cfg.attribute_prefix.as_str())
+        };
+        if *k == cfg.text_key {
+            e.text = scalar_text(v);
+        } else if cfg.preserve_cdata && k == CDATA_KEY {
+            e.cdata = scalar_text(v);
+        } else if let (Some(name), Some(text)) = (attribute, scalar_text(v)) {
+            e.attributes.insert(name.to_string(), text);
+        } else if v.is_array() || v.is_object() || attribute.is_none() {
+            push_elements(k, v, cfg, &mut e.children);
+        }
+    }
+    e
+}
+
+/// Builds an element named `root_name` from the contents of a converted element, using the
+/// default settings; see `object2node_with`.
+pub fn object2node(root_name: &str, obj: &Map<String, Value>) -> treexml::Element {
+    object2node_with(root_name, obj, &Node2ObjectConfig::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use node2object;
+
+    fn round_trip(e: &treexml::Element) -> treexml::Element {
+        let data = node2object(e);
+        let (name, value) = data.iter().next().unwrap();
+        object2node(name, value.as_object().unwrap())
+    }
+
+    #[test]
+    fn round_trip_fixtures() {
+        let mut attributes = treexml::Element::new("player");
+        attributes.attributes.insert("score".into(), "9000".into());
+        let mut text_and_attributes = attributes.clone();
+        text_and_attributes.text = Some("Kolya".into());
+        let parents = treexml::Document::parse(r#"
+            <a pizza="hotdog">
+              <b frenchfry="milkshake">
+                <c>scotch</c>
+              </b>
+            </a>
+        "#.as_bytes()).unwrap().root.unwrap();
+
+        for fixture in &[attributes, text_and_attributes, parents] {
+            let xml = round_trip(fixture);
+            assert_eq!(xml, *fixture);
+            assert_eq!(xml.to_string(), fixture.to_string());
+        }
+    }
+
+    #[test]
+    fn round_trip_is_stable() {
+        let mut server_data = treexml::Element::new("ServerData");
+        for name in &["Kolya", "Petya", "Misha"] {
+            let mut player = treexml::Element::new("Player");
+            player.text = Some(name.to_string());
+            server_data.children.push(player);
+        }
+
+        let once = round_trip(&server_data);
+        assert_eq!(once.to_string(), round_trip(&once).to_string());
+        assert_eq!(node2object(&once), node2object(&server_data));
+    }
+}
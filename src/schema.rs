@@ -0,0 +1,140 @@
+//! JSON Schema (Draft-07) describing converted documents.
+
+use serde_json::{Map, Value};
+use treexml;
+
+use {node2object_with, Node2ObjectConfig};
+
+/// Identifier of the JSON Schema draft the generated schemas follow.
+const DRAFT_07: &str = "http://json-schema.org/draft-07/schema#";
+
+/// JSON Schema type name of `v`.
+fn type_name(v: &Value) -> &'static str {
+    match *v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(ref n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Schema accepting every value in `values`.
+fn infer(values: &[&Value]) -> Value {
+    let mut types: Vec<&str> = Vec::new();
+    for v in values {
+        let t = type_name(v);
+        if !types.contains(&t) {
+            types.push(t);
+        }
+    }
+    if types.contains(&"number") {
+        types.retain(|&t| t != "integer");
+    }
+    types.sort();
+
+    let mut schema = Map::new();
+    match types.len() {
+        0 => {}
+        1 => {
+            schema.insert("type".to_string(), Value::String(types[0].to_string()));
+        }
+        _ => {
+            let types = types.iter().map(|t| Value::String(t.to_string())).collect();
+            schema.insert("type".to_string(), Value::Array(types));
+        }
+    }
+
+    let objects: Vec<&Map<String, Value>> = values.iter().filter_map(|v| v.as_object()).collect();
+    if !objects.is_empty() {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        let mut keys: Vec<&String> = objects.iter().flat_map(|o| o.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let present: Vec<&Value> = objects.iter().filter_map(|o| o.get(key)).collect();
+            if present.len() == objects.len() {
+                required.push(Value::String(key.clone()));
+            }
+            properties.insert(key.clone(), infer(&present));
+        }
+        schema.insert("properties".to_string(), Value::Object(properties));
+        if !required.is_empty() {
+            schema.insert("required".to_string(), Value::Array(required));
+        }
+    }
+
+    let items: Vec<&Value> = values.iter().filter_map(|v| v.as_array()).flatten().collect();
+    if !items.is_empty() {
+        schema.insert("items".to_string(), infer(&items));
+    }
+    Value::Object(schema)
+}
+
+/// Infers a JSON Schema (Draft-07) for what `node2object_with` returns for `e`, from the
+/// converted document itself: `type`, `properties` and `items` as they occur, and `required`
+/// listing the keys present in every object found at the same place (such as every item of
+/// a list). The schema is only as general as the document it is inferred from, and as deep
+/// as `cfg.max_depth` lets the conversion go.
+pub fn infer_json_schema(e: &treexml::Element, cfg: &Node2ObjectConfig) -> Value {
+    let data = Value::Object(node2object_with(e, cfg));
+    let mut schema = match infer(&[&data]) {
+        Value::Object(schema) => schema,
+        _ => Map::new(),
+    };
+    schema.insert("$schema".to_string(), Value::String(DRAFT_07.to_string()));
+    Value::Object(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn population_schema() {
+        let dom_root = treexml::Document::parse("
+            <population>
+              <entry>
+                <name>Alex</name>
+                <height>173.5</height>
+              </entry>
+              <entry>
+                <name>Mel</name>
+                <height>180.4</height>
+              </entry>
+            </population>
+        ".as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig {
+            coerce_numbers: true,
+            ..Node2ObjectConfig::default()
+        };
+
+        assert_eq!(infer_json_schema(&dom_root, &cfg), json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "properties": {
+                "population": {
+                    "type": "object",
+                    "properties": {
+                        "entry": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "height": { "type": "array", "items": { "type": "number" } },
+                                    "name": { "type": "array", "items": { "type": "string" } }
+                                },
+                                "required": ["height", "name"]
+                            }
+                        }
+                    },
+                    "required": ["entry"]
+                }
+            },
+            "required": ["population"]
+        }));
+    }
+}
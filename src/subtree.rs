@@ -0,0 +1,84 @@
+//! Updating a conversion after part of the document changed.
+
+use serde_json::Value;
+use treexml;
+
+use {convert_node_aux, element_key, root_key, tag_name, Context, Node2ObjectConfig};
+
+/// Element of the tree under `e` whose converted value lives at `tokens` (the JSON pointer
+/// tokens below the root key), given as pairs of a child key and an index into its list.
+fn find_element<'e>(e: &'e treexml::Element, tokens: &[String], cfg: &Node2ObjectConfig) -> Option<&'e treexml::Element> {
+    match tokens {
+        [] => Some(e),
+        [key, index, rest @ ..] => {
+            let index: usize = index.parse().ok()?;
+            let child = e
+                .children
+                .iter()
+                .filter(|c| tag_name(c, cfg).is_some_and(|name| element_key(c, &name, cfg) == *key))
+                .nth(index)?;
+            find_element(child, rest, cfg)
+        }
+        _ => None,
+    }
+}
+
+/// Reconverts the element of `e` whose value sits at the JSON pointer `path` of `full`, an
+/// earlier conversion of the same document, and replaces that value in place. The rest of
+/// `full` is left as is. Returns whether `path` was found in both the tree and `full`.
+///
+/// `path` points at an element's value, as in `/population/entry/1`; indexes count the
+/// children with that key in document order, so they only match the output when no sibling
+/// was left out (as empty elements are with `EmptyRepr::Null`). An element that no longer
+/// converts to anything is replaced by `null`.
+pub fn reconvert_subtree(full: &mut Value, e: &treexml::Element, path: &str, cfg: &Node2ObjectConfig) -> bool {
+    let tokens: Vec<String> = path
+        .split('/')
+        .skip(1)
+        .map(|t| t.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    let target = match tokens.split_first() {
+        Some((root, rest)) if root_key(e, cfg) == *root => find_element(e, rest, cfg),
+        _ => None,
+    };
+    let (target, slot) = match (target, full.pointer_mut(path)) {
+        (Some(target), Some(slot)) => (target, slot),
+        _ => return false,
+    };
+    let name = match tag_name(target, cfg) {
+        Some(name) => name,
+        None => return false,
+    };
+    let mut ctx = Context::new(cfg, false);
+    ctx.index_ids(e);
+    *slot = convert_node_aux(target, &name, &mut ctx).unwrap_or(None).unwrap_or(Value::Null);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use node2object;
+
+    #[test]
+    fn reconvert_one_entry() {
+        let mut dom_root = treexml::Document::parse("
+            <population>
+              <entry><name>Alex</name></entry>
+              <entry><name>Mel</name></entry>
+            </population>
+        ".as_bytes()).unwrap().root.unwrap();
+        let cfg = Node2ObjectConfig::default();
+        let mut full = Value::Object(node2object(&dom_root));
+        let first: *const Value = &full["population"]["entry"][0];
+
+        dom_root.children[1].children[0].text = Some("Mia".to_string());
+        assert!(reconvert_subtree(&mut full, &dom_root, "/population/entry/1", &cfg));
+
+        assert_eq!(full, json!({
+            "population": { "entry": [ { "name": ["Alex"] }, { "name": ["Mia"] } ] }
+        }));
+        assert!(std::ptr::eq(first, &full["population"]["entry"][0]));
+        assert!(!reconvert_subtree(&mut full, &dom_root, "/population/entry/2", &cfg));
+    }
+}
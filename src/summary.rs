@@ -0,0 +1,131 @@
+//! Outline of a document's structure, for getting to know a document before converting it.
+
+use treexml;
+
+use {parse_text, Node2ObjectConfig};
+
+/// Type of a text or attribute value, as `coerce_numbers` would see it.
+fn value_type(text: &str, tag: &str, cfg: &Node2ObjectConfig) -> &'static str {
+    if parse_text(text, tag, cfg).is_number() {
+        "number"
+    } else {
+        "string"
+    }
+}
+
+/// Common type of several values: their type if they agree, `string` otherwise.
+fn merge_types<'t, I: Iterator<Item = &'t str>>(mut types: I) -> Option<&'t str> {
+    let first = types.next()?;
+    Some(if types.all(|t| t == first) { first } else { "string" })
+}
+
+/// Writes the outline of the elements in `group`, which share a tag name and occur up to
+/// `count` times in their parent. Children below `max_depth` are written as `...`.
+fn describe(group: &[&treexml::Element], count: usize, depth: usize, cfg: &Node2ObjectConfig, out: &mut String) {
+    let name = &group[0].name;
+    let indent = "  ".repeat(depth);
+    out.push_str(&indent);
+    out.push_str(name);
+    if count > 1 {
+        out.push_str(&format!("[{}]", count));
+    }
+    let texts = group.iter().filter_map(|e| e.text.as_ref().or(e.cdata.as_ref()));
+    if let Some(t) = merge_types(texts.map(|text| value_type(text, name, cfg))) {
+        out.push_str(": ");
+        out.push_str(t);
+    }
+    out.push('\n');
+
+    let mut attributes: Vec<&String> = group.iter().flat_map(|e| e.attributes.keys()).collect();
+    attributes.sort();
+    attributes.dedup();
+    for attribute in attributes {
+        let values = group.iter().filter_map(|e| e.attributes.get(attribute));
+        let t = merge_types(values.map(|value| value_type(value, name, cfg))).unwrap_or("string");
+        out.push_str(&format!("{}  @{}: {}\n", indent, attribute, t));
+    }
+
+    if cfg.max_depth.is_some_and(|max| depth + 2 > max) {
+        if group.iter().any(|e| !e.children.is_empty()) {
+            out.push_str(&format!("{}  ...\n", indent));
+        }
+        return;
+    }
+    let mut tags: Vec<&String> = Vec::new();
+    for c in group.iter().flat_map(|e| &e.children) {
+        if !tags.contains(&&c.name) {
+            tags.push(&c.name);
+        }
+    }
+    for tag in tags {
+        let children: Vec<&treexml::Element> = group
+            .iter()
+            .flat_map(|e| &e.children)
+            .filter(|c| c.name == *tag)
+            .collect();
+        let count = group
+            .iter()
+            .map(|e| e.children.iter().filter(|c| c.name == *tag).count())
+            .max()
+            .unwrap_or(0);
+        describe(&children, count, depth + 1, cfg, out);
+    }
+}
+
+/// Renders an indented outline of the structure of `e`: one line per tag name, with the
+/// largest number of times it repeats within a parent (`entry[2]`), the type of its text
+/// (`number` or `string`), and its attributes prefixed with `@`. Repeated elements are
+/// merged, so the children listed under `entry[2]` are those of every `entry`. Levels
+/// deeper than `DEFAULT_MAX_DEPTH` are left out.
+pub fn summarize(e: &treexml::Element) -> String {
+    let cfg = Node2ObjectConfig {
+        coerce_numbers: true,
+        ..Node2ObjectConfig::default()
+    };
+    let mut out = String::new();
+    describe(&[e], 1, 0, &cfg, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use DEFAULT_MAX_DEPTH;
+
+    #[test]
+    fn population_summary() {
+        let dom_root = treexml::Document::parse("
+            <population>
+              <entry>
+                <name>Alex</name>
+                <height>173.5</height>
+              </entry>
+              <entry>
+                <name>Mel</name>
+                <height>180.4</height>
+              </entry>
+            </population>
+        ".as_bytes()).unwrap().root.unwrap();
+
+        assert_eq!(summarize(&dom_root), "population\n  entry[2]\n    name: string\n    height: number\n");
+    }
+
+    #[test]
+    fn deep_summary() {
+        let mut chain = treexml::Element::new("a");
+        for _ in 1..50_000 {
+            let mut parent = treexml::Element::new("a");
+            parent.children.push(chain);
+            chain = parent;
+        }
+
+        let summary = summarize(&chain);
+        assert_eq!(summary.lines().count(), DEFAULT_MAX_DEPTH + 1);
+        assert_eq!(summary.lines().last().map(str::trim), Some("..."));
+
+        let mut pending = vec![chain];
+        while let Some(mut e) = pending.pop() {
+            pending.append(&mut e.children);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! TypeScript declarations describing the output of `node2object`.
+
+use serde_json::Value;
+use inflector::cases::pascalcase::to_pascal_case;
+use treexml;
+
+use node2object;
+
+/// Interfaces generated so far, as `(name, body)` in order of first use.
+type Interfaces = Vec<(String, String)>;
+
+/// Property name, quoted unless it is a valid identifier.
+fn property_name(key: &str) -> String {
+    let mut chars = key.chars();
+    let is_identifier = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if is_identifier {
+        key.to_string()
+    } else {
+        format!("{:?}", key)
+    }
+}
+
+/// Union of the types of `values`, found under `key`; objects become interfaces named after it.
+fn union_type(values: &[&Value], key: &str, interfaces: &mut Interfaces) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let items: Vec<&Value> = values.iter().filter_map(|v| v.as_array()).flatten().collect();
+    if values.iter().any(|v| v.is_array()) {
+        let item = union_type(&items, key, interfaces);
+        parts.push(if item.contains(' ') {
+            format!("({})[]", item)
+        } else {
+            format!("{}[]", item)
+        });
+    }
+    let objects: Vec<&Value> = values.iter().cloned().filter(|v| v.is_object()).collect();
+    if !objects.is_empty() {
+        parts.push(interface(&to_pascal_case(key), &objects, interfaces));
+    }
+    for v in values {
+        let scalar = match **v {
+            Value::String(_) => "string",
+            Value::Number(_) => "number",
+            Value::Bool(_) => "boolean",
+            Value::Null => "null",
+            _ => continue,
+        };
+        if !parts.iter().any(|p| p == scalar) {
+            parts.push(scalar.to_string());
+        }
+    }
+    if parts.is_empty() {
+        "unknown".to_string()
+    } else {
+        parts.join(" | ")
+    }
+}
+
+/// Declares an interface `name` covering every object in `objects`, unless one with that
+/// name exists already, and returns its name. Properties come in key order; keys missing
+/// from some objects are optional.
+fn interface(name: &str, objects: &[&Value], interfaces: &mut Interfaces) -> String {
+    if interfaces.iter().any(|(n, _)| n == name) {
+        return name.to_string();
+    }
+    let slot = interfaces.len();
+    interfaces.push((name.to_string(), String::new()));
+    let mut keys: Vec<&String> = Vec::new();
+    for object in objects {
+        for key in object.as_object().into_iter().flat_map(|o| o.keys()) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    let mut body = String::new();
+    for key in keys {
+        let values: Vec<&Value> = objects.iter().filter_map(|o| o.get(key)).collect();
+        let optional = if values.len() < objects.len() { "?" } else { "" };
+        let t = union_type(&values, key, interfaces);
+        body.push_str(&format!("  {}{}: {};\n", property_name(key), optional, t));
+    }
+    interfaces[slot].1 = body;
+    name.to_string()
+}
+
+/// Generates TypeScript declarations for what `node2object` returns for `e`: an interface
+/// `name` for the value under the root key, plus one interface per object-valued key, named
+/// after the key in PascalCase (`entry` objects become `Entry`). Types are inferred from the
+/// converted document itself, so properties are only as complete as the sample; a key that
+/// occurs in more than one place gets the interface inferred where it first occurs.
+/// Like the conversion, inference stops at `DEFAULT_MAX_DEPTH`.
+pub fn to_typescript_interface(e: &treexml::Element, name: &str) -> String {
+    let data = node2object(e);
+    let root = match data.values().next() {
+        Some(root) if root.is_object() => root,
+        Some(root) => {
+            let t = union_type(&[root], name, &mut Vec::new());
+            return format!("export type {} = {};\n", name, t);
+        }
+        None => return String::new(),
+    };
+    let mut interfaces = Vec::new();
+    interface(name, &[root], &mut interfaces);
+    interfaces
+        .iter()
+        .map(|(name, body)| format!("export interface {} {{\n{}}}\n", name, body))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn population_interface() {
+        let dom_root = treexml::Document::parse(r#"
+            <population>
+              <entry id="1">
+                <name>Alex</name>
+                <height>173.5</height>
+              </entry>
+              <entry id="2">
+                <name>Mel</name>
+              </entry>
+            </population>
+        "#.as_bytes()).unwrap().root.unwrap();
+
+        assert_eq!(to_typescript_interface(&dom_root, "Population"), "\
+export interface Population {
+  entry: Entry[];
+}
+
+export interface Entry {
+  height?: string[];
+  id: string;
+  name: string[];
+}
+");
+    }
+}